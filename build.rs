@@ -1,3 +1,16 @@
 fn main() {
-    prost_build::compile_protos(&["src/spectrogram.proto"], &["src/"]).unwrap();
+    let mut config = prost_build::Config::new();
+    // Only derives anything when the `serde` feature is enabled, as the generated code is compiled as part of the crate.
+    config.type_attribute(
+        ".",
+        "#[cfg_attr(feature = \"serde\", derive(serde::Serialize, serde::Deserialize))]",
+    );
+    // The header is much larger than a column block, but chunks are only ever handled one at a time.
+    config.type_attribute(
+        ".tizol.SpectrogramChunk.chunk",
+        "#[allow(clippy::large_enum_variant)]",
+    );
+    config
+        .compile_protos(&["src/spectrogram.proto"], &["src/"])
+        .unwrap();
 }
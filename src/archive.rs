@@ -0,0 +1,245 @@
+/*!
+ * A container format for storing many spectrograms in a single file.
+ *
+ * Writing one protobuf file per track quickly leads to hundreds of thousands of tiny files. Instead, an archive stores length delimited `Spectrogram` records one after another, followed by an `ArchiveIndex` mapping keys (e.g. track IDs, or the content hash of the source audio) to the location of each record. A reader only needs to read the index, and can then seek straight to, and decode, a single spectrogram.
+ *
+ * The layout of an archive is:
+ *
+ * ```text
+ * "TIZOLARC"                      8 byte magic
+ * spectrogram record *            length delimited `Spectrogram` messages
+ * index                           `ArchiveIndex` message
+ * index offset                    u64, little endian
+ * "TZOLINDX"                      8 byte magic
+ * ```
+ *
+ * Records may use any of the storage encodings in `Encoding`, and are expanded as they are read.
+ */
+use super::{ArchiveEntry, ArchiveIndex, Spectrogram};
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use prost::Message;
+
+const HEADER_MAGIC: &[u8; 8] = b"TIZOLARC";
+const FOOTER_MAGIC: &[u8; 8] = b"TZOLINDX";
+
+/// The size of the footer: the index offset, and the footer magic.
+const FOOTER_SIZE: u64 = 16;
+
+/// Appends spectrograms to an archive.
+///
+/// The index is written when the writer is finished (or dropped), so an archive is only readable once its writer has been finished.
+pub struct ArchiveWriter<W: Write + Seek> {
+    writer: W,
+    offset: u64,
+    entries: Vec<ArchiveEntry>,
+    finished: bool,
+}
+
+impl ArchiveWriter<BufWriter<File>> {
+    /// Creates a new, empty archive at `path`, replacing any existing file.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+
+    /// Opens an existing archive at `path` to append more spectrograms to it, or creates a new one if it doesn't exist.
+    pub fn append_to<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        if !path.as_ref().exists() {
+            return Self::create(path);
+        }
+
+        let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+        let (index_offset, index) = read_index(&mut file)?;
+
+        // Drop the old index, and carry on writing records where it used to be.
+        file.set_len(index_offset)?;
+        file.seek(SeekFrom::Start(index_offset))?;
+
+        Ok(ArchiveWriter {
+            writer: BufWriter::new(file),
+            offset: index_offset,
+            entries: index.entries,
+            finished: false,
+        })
+    }
+}
+
+impl<W: Write + Seek> ArchiveWriter<W> {
+    /// Starts a new archive in an arbitrary writer.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(HEADER_MAGIC)?;
+        Ok(ArchiveWriter {
+            writer,
+            offset: HEADER_MAGIC.len() as u64,
+            entries: Vec::new(),
+            finished: false,
+        })
+    }
+
+    /// Appends a spectrogram to the archive under `key`.
+    ///
+    /// If a spectrogram has already been stored under `key`, the new one replaces it in the index (although the old record is still present in the file).
+    pub fn append(&mut self, key: &str, spectrogram: &Spectrogram) -> io::Result<()> {
+        let mut record = Vec::with_capacity(spectrogram.encoded_len() + 10);
+        spectrogram.encode_length_delimited(&mut record)?;
+        self.writer.write_all(&record[..])?;
+
+        self.entries.push(ArchiveEntry {
+            key: key.to_string(),
+            offset: self.offset,
+            length: record.len() as u64,
+        });
+        self.offset += record.len() as u64;
+        Ok(())
+    }
+
+    /// Writes the index, and flushes the archive.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.finish_internal()
+    }
+
+    fn finish_internal(&mut self) -> io::Result<()> {
+        self.finished = true;
+
+        let index = ArchiveIndex {
+            entries: std::mem::take(&mut self.entries),
+        };
+        let mut buf = Vec::with_capacity(index.encoded_len());
+        index.encode(&mut buf)?;
+
+        self.writer.write_all(&buf[..])?;
+        self.writer.write_all(&self.offset.to_le_bytes())?;
+        self.writer.write_all(FOOTER_MAGIC)?;
+        self.writer.flush()
+    }
+}
+
+impl<W: Write + Seek> Drop for ArchiveWriter<W> {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.finish_internal();
+        }
+    }
+}
+
+/// Reads individual spectrograms from an archive.
+pub struct ArchiveReader<R: Read + Seek> {
+    reader: R,
+    entries: HashMap<String, ArchiveEntry>,
+}
+
+impl ArchiveReader<BufReader<File>> {
+    /// Opens the archive at `path`, reading its index.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read + Seek> ArchiveReader<R> {
+    /// Reads the index of an archive from an arbitrary reader.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let (_, index) = read_index(&mut reader)?;
+
+        // Later entries replace earlier ones with the same key
+        let entries = index
+            .entries
+            .into_iter()
+            .map(|e| (e.key.clone(), e))
+            .collect();
+
+        Ok(ArchiveReader { reader, entries })
+    }
+
+    /// The number of (distinct) spectrograms in the archive.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The keys of the spectrograms in the archive, in no particular order.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(|k| &k[..])
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Reads and decodes the spectrogram stored under `key`, without reading any of the others.
+    ///
+    /// Returns `Ok(None)` if there is no spectrogram stored under `key`.
+    pub fn get(&mut self, key: &str) -> io::Result<Option<Spectrogram>> {
+        let (offset, length) = match self.entries.get(key) {
+            Some(entry) => (entry.offset, entry.length),
+            None => return Ok(None),
+        };
+
+        self.reader.seek(SeekFrom::Start(offset))?;
+        let mut record = vec![0; length as usize];
+        self.reader.read_exact(&mut record[..])?;
+
+        let mut spectrogram = Spectrogram::decode_length_delimited(&record[..])?;
+        spectrogram.expand()?;
+        Ok(Some(spectrogram))
+    }
+}
+
+/// Reads the footer and index of an archive, returning the offset of the index and the index itself.
+fn read_index<R: Read + Seek>(reader: &mut R) -> io::Result<(u64, ArchiveIndex)> {
+    let mut magic = [0; 8];
+    reader.seek(SeekFrom::Start(0))?;
+    reader.read_exact(&mut magic)?;
+    if &magic != HEADER_MAGIC {
+        return Err(invalid_data("not a tizol archive"));
+    }
+
+    let end = reader.seek(SeekFrom::End(0))?;
+    if end < HEADER_MAGIC.len() as u64 + FOOTER_SIZE {
+        return Err(invalid_data("archive is missing its index"));
+    }
+
+    let mut footer = [0; FOOTER_SIZE as usize];
+    reader.seek(SeekFrom::Start(end - FOOTER_SIZE))?;
+    reader.read_exact(&mut footer)?;
+    if &footer[8..] != FOOTER_MAGIC {
+        return Err(invalid_data(
+            "archive is missing its index (was the writer finished?)",
+        ));
+    }
+
+    let mut offset_bytes = [0; 8];
+    offset_bytes.copy_from_slice(&footer[..8]);
+    let index_offset = u64::from_le_bytes(offset_bytes);
+    if index_offset < HEADER_MAGIC.len() as u64 || index_offset > end - FOOTER_SIZE {
+        return Err(invalid_data("archive index offset is out of range"));
+    }
+
+    let mut buf = vec![0; (end - FOOTER_SIZE - index_offset) as usize];
+    reader.seek(SeekFrom::Start(index_offset))?;
+    reader.read_exact(&mut buf[..])?;
+
+    // Check the entries before anything is allocated for them
+    let index = ArchiveIndex::decode(&buf[..])?;
+    for entry in &index.entries {
+        let in_range = match entry.offset.checked_add(entry.length) {
+            Some(end) => entry.offset >= HEADER_MAGIC.len() as u64 && end <= index_offset,
+            None => false,
+        };
+        if !in_range {
+            return Err(invalid_data("archive index entry is out of range"));
+        }
+    }
+
+    Ok((index_offset, index))
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
@@ -0,0 +1,60 @@
+/*!
+ * Conversions between spectrograms and `ndarray` arrays (requires the `ndarray` feature).
+ *
+ * Arrays have `(frequency, time)` axes, as in librosa, so `view()[[bin, frame]]` is the value of frequency bin `bin` in column (frame) `frame`. As spectrograms store their data column by column, views are Fortran (column major) ordered, and borrow `data` directly without copying.
+ *
+ * ```ignore
+ * let spectrogram = Spectrogram::from_file("track.mp3")?;
+ * // The mean of each frequency bin across the whole track
+ * let profile = spectrogram.view().mean_axis(Axis(1));
+ * ```
+ */
+use super::Spectrogram;
+
+use ndarray::{Array2, ArrayBase, ArrayView2, ArrayViewMut2, Data, Ix2, ShapeBuilder};
+
+impl Spectrogram {
+    /// A `(height, width)` view of the spectrogram data, with frequency bins along the first axis, and frames along the second.
+    ///
+    /// # Panics
+    /// panics if `data` doesn't match the dimensions of the spectrogram (e.g. if it is compactly encoded, and hasn't been expanded)
+    pub fn view(&self) -> ArrayView2<'_, f64> {
+        ArrayView2::from_shape(self.shape().f(), &self.data[..])
+            .expect("spectrogram data does not match its dimensions")
+    }
+
+    /// A mutable `(height, width)` view of the spectrogram data. See `view`.
+    ///
+    /// # Panics
+    /// panics if `data` doesn't match the dimensions of the spectrogram
+    pub fn view_mut(&mut self) -> ArrayViewMut2<'_, f64> {
+        let shape = self.shape();
+        ArrayViewMut2::from_shape(shape.f(), &mut self.data[..])
+            .expect("spectrogram data does not match its dimensions")
+    }
+
+    /// Copies the spectrogram data into an owned `(height, width)` array.
+    pub fn to_array(&self) -> Array2<f64> {
+        self.view().to_owned()
+    }
+
+    /// Creates a spectrogram from a `(frequency, time)` array of any memory layout.
+    ///
+    /// The new spectrogram has no parameters or source, as they can't be known from the array.
+    pub fn from_array<S: Data<Elem = f64>>(array: &ArrayBase<S, Ix2>) -> Self {
+        let (height, width) = array.dim();
+        // Iterating over the transpose in logical order visits the array column by column
+        let data = array.t().iter().cloned().collect();
+
+        Spectrogram {
+            width: width as u32,
+            height: height as u32,
+            data,
+            ..Default::default()
+        }
+    }
+
+    fn shape(&self) -> (usize, usize) {
+        (self.height as usize, self.width as usize)
+    }
+}
@@ -0,0 +1,374 @@
+/*!
+ * Configurable spectrogram computation.
+ *
+ * `Spectrogram::from_buffer` and `Spectrogram::from_file` compute spectrograms with a fixed set of parameters, chosen to match librosa's defaults. A `SpectrogramBuilder` allows those parameters to be changed, e.g. to compute dB values relative to a fixed reference rather than the loudest value in each track:
+ *
+ * ```ignore
+ * let spectrogram = SpectrogramBuilder::new()
+ *     .reference(Reference::Value(1.0))
+ *     .top_db(None)
+ *     .build_from_file("track.mp3")?;
+ * ```
+ *
+ * A constant-Q transform can be computed instead of an STFT (see the `cqt` module), and magnitudes can be scaled with PCEN (see the `pcen` module), or left as they are, rather than converted to dB. Harmonic and percussive components can be separated before scaling (see the `hpss` module). The parameters used are recorded in the spectrogram's `parameters`.
+ */
+use super::cqt::Cqt;
+use super::db::{self, Reference};
+use super::frame;
+use super::hpss::Hpss;
+use super::normalisation::Normalisation;
+use super::pcen::Pcen;
+use super::stft::inplace::STFT as InplaceSTFT;
+use super::stft::inverse::ISTFT;
+use super::stft::WindowType;
+use super::{content_hash, Parameters, Source, Spectrogram};
+use super::{AMIN, SAMPLE_RATE, SCHEMA_VERSION, STEP_SIZE, TOP_DB, WINDOW_SIZE};
+
+use hodges::*;
+
+use std::path::PathBuf;
+
+/// How STFT (or CQT) magnitudes are scaled, before they are normalised.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Scaling {
+    /// Convert magnitudes to dB, as configured by `reference`, `amin` and `top_db`.
+    Db,
+    /// Apply per-channel energy normalisation.
+    Pcen(Pcen),
+    /// Leave the magnitudes as they are, e.g. for computing chroma features.
+    Magnitude,
+}
+
+impl std::fmt::Display for Scaling {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Scaling::Db => write!(formatter, "db"),
+            Scaling::Pcen(pcen) => write!(formatter, "{}", pcen),
+            Scaling::Magnitude => write!(formatter, "magnitude"),
+        }
+    }
+}
+
+/// Computes spectrograms with configurable parameters. The default parameters are those used by `Spectrogram::from_buffer`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpectrogramBuilder {
+    window_type: WindowType,
+    window_size: usize,
+    step_size: usize,
+    reference: Reference,
+    amin: f64,
+    top_db: Option<f64>,
+    cqt: Option<Cqt>,
+    scaling: Scaling,
+    normalisation: Normalisation,
+}
+
+impl Default for SpectrogramBuilder {
+    fn default() -> Self {
+        SpectrogramBuilder {
+            window_type: WindowType::Hanning,
+            window_size: WINDOW_SIZE,
+            step_size: STEP_SIZE,
+            reference: Reference::Max,
+            amin: AMIN,
+            top_db: Some(TOP_DB),
+            cqt: None,
+            scaling: Scaling::Db,
+            normalisation: Normalisation::InvertedMinMax,
+        }
+    }
+}
+
+impl SpectrogramBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The STFT window function (default `WindowType::Hanning`).
+    pub fn window_type(mut self, window_type: WindowType) -> Self {
+        self.window_type = window_type;
+        self
+    }
+
+    /// The STFT window (and FFT) size, in samples (default `WINDOW_SIZE`).
+    pub fn window_size(mut self, window_size: usize) -> Self {
+        self.window_size = window_size;
+        self
+    }
+
+    /// The STFT step (hop) size, in samples (default `STEP_SIZE`).
+    pub fn step_size(mut self, step_size: usize) -> Self {
+        self.step_size = step_size;
+        self
+    }
+
+    /// The reference that dB values are relative to (default `Reference::Max`).
+    pub fn reference(mut self, reference: Reference) -> Self {
+        self.reference = reference;
+        self
+    }
+
+    /// The minimum amplitude considered when converting to dB (default `AMIN`).
+    pub fn amin(mut self, amin: f64) -> Self {
+        self.amin = amin;
+        self
+    }
+
+    /// The dynamic range (in dB) below the peak to clip the spectrogram to, or `None` to disable clipping (default `Some(TOP_DB)`).
+    pub fn top_db(mut self, top_db: Option<f64>) -> Self {
+        self.top_db = top_db;
+        self
+    }
+
+    /// Computes a constant-Q transform instead of an STFT. The columns of the spectrogram are the same as those of the STFT, i.e. frames of `window_size` samples every `step_size` samples.
+    pub fn cqt(mut self, cqt: Cqt) -> Self {
+        self.cqt = Some(cqt);
+        self
+    }
+
+    /// How magnitudes are scaled before they are normalised (default `Scaling::Db`). Unless this is `Scaling::Db`, the dB conversion parameters are ignored.
+    pub fn scaling(mut self, scaling: Scaling) -> Self {
+        self.scaling = scaling;
+        self
+    }
+
+    /// Scales the magnitudes with PCEN, instead of converting them to dB. A shorthand for `scaling(Scaling::Pcen(pcen))`.
+    ///
+    /// PCEN values are non-negative, and larger for louder values, so should be normalised with `Normalisation::MinMax` (or not at all) rather than the default normalisation.
+    pub fn pcen(self, pcen: Pcen) -> Self {
+        self.scaling(Scaling::Pcen(pcen))
+    }
+
+    /// How values are normalised after the dB conversion (default `Normalisation::InvertedMinMax`).
+    pub fn normalisation(mut self, normalisation: Normalisation) -> Self {
+        self.normalisation = normalisation;
+        self
+    }
+
+    /// Computes a spectrogram from an audio file, decoded with libhodges.
+    ///
+    /// Returns `None` if `State::from_file()` fails for any reason.
+    pub fn build_from_file<P: Into<PathBuf>>(&self, filename: P) -> Option<Spectrogram> {
+        let path: PathBuf = filename.into();
+
+        // Create a hodges state object to load the audio data
+        let state: State<f32> = State::from_file(path.clone())?;
+
+        // Collect the audio samples into a single buffer for processing.
+        let audio_samples: Vec<f64> = state.map(|f| f as f64).collect();
+
+        let mut spectrogram = self.build(&audio_samples);
+
+        // Record where the samples came from, so that we can identify stale or mismatched spectrograms later.
+        let source = spectrogram.source.get_or_insert_with(Source::default);
+        source.path = path.to_string_lossy().into_owned();
+        if let Ok(bytes) = std::fs::read(&path) {
+            source.content_hash = content_hash(&bytes[..]);
+        }
+
+        Some(spectrogram)
+    }
+
+    /// The RMS energy of each frame of some samples, aligned column for column with the spectrogram computed by `build` (see the `frame` module).
+    pub fn rms(&self, audio_samples: &[f64]) -> Vec<f64> {
+        frame::rms(audio_samples, self.window_size, self.step_size)
+    }
+
+    /// The zero-crossing rate of each frame of some samples, aligned column for column with the spectrogram computed by `build`.
+    pub fn zero_crossing_rate(&self, audio_samples: &[f64]) -> Vec<f64> {
+        frame::zero_crossing_rate(audio_samples, self.window_size, self.step_size)
+    }
+
+    /// Computes a spectrogram from single channel PCM samples at `SAMPLE_RATE`.
+    pub fn build(&self, audio_samples: &[f64]) -> Spectrogram {
+        let (magnitudes, height, parameters) = self.magnitudes(audio_samples);
+        self.finish(magnitudes, height, parameters, audio_samples.len())
+    }
+
+    /// Computes spectrograms of the harmonic and percussive components of some samples (see the `hpss` module).
+    ///
+    /// The magnitudes are separated before they are scaled and normalised, and the `component` of each spectrogram's parameters is set to "harmonic" or "percussive".
+    pub fn build_hpss(&self, hpss: &Hpss, audio_samples: &[f64]) -> (Spectrogram, Spectrogram) {
+        let (magnitudes, height, parameters) = self.magnitudes(audio_samples);
+        let (harmonic_mask, percussive_mask) = hpss.masks(&magnitudes[..], height);
+
+        let component = |mask: Vec<f64>, name: &str| {
+            let masked = magnitudes.iter().zip(mask.iter()).map(|(v, m)| v * m).collect();
+            let parameters = Parameters {
+                component: name.to_string(),
+                ..parameters.clone()
+            };
+            self.finish(masked, height, parameters, audio_samples.len())
+        };
+        (
+            component(harmonic_mask, "harmonic"),
+            component(percussive_mask, "percussive"),
+        )
+    }
+
+    /// Separates some samples into their harmonic and percussive components (see the `hpss` module), returning audio of the same length as the input.
+    ///
+    /// The masks are computed from the STFT magnitudes, applied to the complex STFT, which is then inverted. This always uses the STFT, even if a constant-Q transform has been configured. Any samples after the last complete frame are zero.
+    pub fn separate(&self, hpss: &Hpss, audio_samples: &[f64]) -> (Vec<f64>, Vec<f64>) {
+        let stft = InplaceSTFT::<f64>::new(self.window_type, self.window_size, self.step_size);
+        let height = stft.output_size();
+        let columns = stft.par_iter_complex_stft(audio_samples);
+
+        let magnitudes: Vec<f64> = columns
+            .iter()
+            .flat_map(|column| column[..height].iter().map(|c| c.norm()))
+            .collect();
+        let (harmonic_mask, percussive_mask) = hpss.masks(&magnitudes[..], height);
+
+        let istft = ISTFT::<f64>::new(self.window_type, self.window_size, self.step_size);
+        let invert = |mask: Vec<f64>| {
+            let masked: Vec<_> = columns
+                .iter()
+                .zip(mask.chunks(height.max(1)))
+                .map(|(column, mask)| {
+                    // Negative frequency bins share the mask of their positive counterparts, and the Nyquist bin (which isn't in the spectrogram) that of the highest bin.
+                    let n = column.len();
+                    column
+                        .iter()
+                        .enumerate()
+                        .map(|(k, c)| c * mask[k.min(n - k).min(height - 1)])
+                        .collect()
+                })
+                .collect();
+            istft.istft(&masked[..], audio_samples.len())
+        };
+        (invert(harmonic_mask), invert(percussive_mask))
+    }
+
+    /// The STFT (or CQT) magnitudes of some samples, along with the height and parameters of the spectrogram.
+    fn magnitudes(&self, audio_samples: &[f64]) -> (Vec<f64>, usize, Parameters) {
+        match self.cqt {
+            Some(cqt) => self.constant_q(cqt, audio_samples),
+            None => self.stft(audio_samples),
+        }
+    }
+
+    /// Scales and normalises magnitudes, and wraps them up as a spectrogram.
+    fn finish(
+        &self,
+        mut spectrogram_output: Vec<f64>,
+        height: usize,
+        parameters: Parameters,
+        sample_count: usize,
+    ) -> Spectrogram {
+        // Compute the amplitude_to_db (or PCEN) of the result.
+        match self.scaling {
+            Scaling::Db => db::amplitude_to_db(
+                &mut spectrogram_output[..],
+                &self.reference,
+                self.amin,
+                self.top_db,
+            ),
+            Scaling::Pcen(pcen) => pcen.apply(
+                &mut spectrogram_output[..],
+                height,
+                SAMPLE_RATE,
+                self.step_size,
+            ),
+            Scaling::Magnitude => {}
+        }
+
+        // Normalize the output.
+        self.normalisation.apply(&mut spectrogram_output[..], height);
+
+        // Finally, calculate the width of the data (there are no columns if there are no rows).
+        let width = spectrogram_output.len().checked_div(height).unwrap_or(0);
+
+        let source = Source {
+            duration: sample_count as f64 / SAMPLE_RATE as f64,
+            ..Default::default()
+        };
+
+        Spectrogram {
+            data: spectrogram_output,
+            width: width as u32,
+            height: height as u32,
+            schema_version: SCHEMA_VERSION,
+            parameters: Some(parameters),
+            source: Some(source),
+            created: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            ..Default::default()
+        }
+    }
+
+    /// Computes the STFT magnitudes of some samples, along with the height and parameters of the spectrogram.
+    fn stft(&self, audio_samples: &[f64]) -> (Vec<f64>, usize, Parameters) {
+        // Initialise the stft machinery.
+        let stft = InplaceSTFT::<f64>::new(self.window_type, self.window_size, self.step_size);
+
+        // Adjustable - for now, use the whole buffer.
+        let low_freq = 0;
+        let hi_freq = stft.output_size();
+
+        // Perform the STFT across the samples
+        let magnitudes = stft.par_iter_stft(audio_samples);
+
+        let parameters = Parameters {
+            fft_size: self.window_size as u32,
+            fmin: self.bin_frequency(low_freq),
+            fmax: self.bin_frequency(hi_freq),
+            ..self.parameters()
+        };
+        (magnitudes, hi_freq - low_freq, parameters)
+    }
+
+    /// Computes the constant-Q magnitudes of some samples, along with the height and parameters of the spectrogram.
+    fn constant_q(&self, cqt: Cqt, audio_samples: &[f64]) -> (Vec<f64>, usize, Parameters) {
+        let kernel = cqt.kernel(self.window_type, SAMPLE_RATE);
+        let magnitudes = kernel.compute(audio_samples, self.window_size, self.step_size);
+
+        let parameters = Parameters {
+            fft_size: kernel.fft_size() as u32,
+            fmin: cqt.frequency(0),
+            fmax: cqt.frequency(cqt.n_bins),
+            bins_per_octave: cqt.bins_per_octave as u32,
+            filter_scale: cqt.filter_scale,
+            ..self.parameters()
+        };
+        (magnitudes, kernel.height(), parameters)
+    }
+
+    /// The parameters that are independent of the transform.
+    fn parameters(&self) -> Parameters {
+        Parameters {
+            sample_rate: SAMPLE_RATE,
+            window: self.window_type.to_string(),
+            window_size: self.window_size as u32,
+            step_size: self.step_size as u32,
+            normalisation: self.normalisation.to_string(),
+            ..self.scaling_parameters()
+        }
+    }
+
+    /// The parameters describing how magnitudes are scaled.
+    fn scaling_parameters(&self) -> Parameters {
+        match self.scaling {
+            Scaling::Db => Parameters {
+                scaling: self.scaling.to_string(),
+                db_reference: self.reference.to_string(),
+                amin: self.amin,
+                top_db: self.top_db.unwrap_or(0.0),
+                ..Default::default()
+            },
+            _ => Parameters {
+                scaling: self.scaling.to_string(),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// The frequency (in Hz) of an STFT bin.
+    fn bin_frequency(&self, bin: usize) -> f64 {
+        bin as f64 * SAMPLE_RATE as f64 / self.window_size as f64
+    }
+}
@@ -0,0 +1,253 @@
+/*!
+ * Chroma features, tuning estimation and key estimation.
+ *
+ * A chromagram folds the energy of every frequency bin of a spectrogram into one of the twelve pitch classes (C, C#, D, ..., B), discarding the octave. Chromagrams can be computed from STFT or constant-Q spectrograms, and are themselves returned as spectrograms with twelve rows (starting at C), so that they can be indexed, sliced, rendered and saved like any other spectrogram.
+ *
+ * Chroma is computed from the squared values of the spectrogram, so for results comparable with `librosa.feature.chroma_stft`, compute the spectrogram with `Scaling::Magnitude` and `Normalisation::None`:
+ *
+ * ```ignore
+ * let spectrogram = SpectrogramBuilder::new()
+ *     .scaling(Scaling::Magnitude)
+ *     .normalisation(Normalisation::None)
+ *     .build(&samples);
+ * let key = spectrogram.estimate_key().unwrap();
+ * println!("{} ({:.2})", key, key.confidence);
+ * ```
+ */
+use super::cqt::C1;
+use super::Spectrogram;
+
+use std::fmt;
+
+/// The names of the pitch classes, in the order of the rows of a chromagram.
+pub const PITCH_CLASSES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+/// The Krumhansl-Kessler key profile of C major.
+const MAJOR_PROFILE: [f64; 12] = [
+    6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+
+/// The Krumhansl-Kessler key profile of C minor.
+const MINOR_PROFILE: [f64; 12] = [
+    6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
+
+/// The frequency range (in Hz) of the peaks used to estimate tuning, as in `librosa.piptrack`.
+const TUNING_FMIN: f64 = 150.0;
+const TUNING_FMAX: f64 = 4000.0;
+
+/// The resolution (in semitones) of tuning estimates.
+const TUNING_RESOLUTION: f64 = 0.01;
+
+impl Spectrogram {
+    /// Estimates the deviation (in fractions of a semitone, in [-0.5,0.5]) of the tuning of the audio from A440, as `librosa.estimate_tuning`.
+    ///
+    /// The deviation of each spectral peak from the nearest semitone is measured, and the most common deviation is returned. Returns 0 if the spectrogram has no peaks.
+    pub fn estimate_tuning(&self) -> f64 {
+        let steps = (1.0 / TUNING_RESOLUTION).round() as usize;
+        let mut histogram = vec![0.0; steps + 1];
+
+        let height = self.height as usize;
+        for column in self.columns() {
+            let mean = column.iter().sum::<f64>() / height as f64;
+
+            for bin in 1..height.saturating_sub(1) {
+                let (a, b, c) = (column[bin - 1], column[bin], column[bin + 1]);
+                if b <= mean || b <= a || b < c {
+                    continue;
+                }
+
+                // Refine the peak frequency with parabolic interpolation
+                let shift = 0.5 * (a - c) / (a - 2.0 * b + c);
+                let frequency = self.fractional_bin_to_hz(bin, shift);
+                if !(TUNING_FMIN..=TUNING_FMAX).contains(&frequency) {
+                    continue;
+                }
+
+                let pitch = hz_to_pitch(frequency);
+                let deviation = pitch - pitch.round();
+                histogram[((deviation + 0.5) * steps as f64).round() as usize] += b;
+            }
+        }
+
+        // The most common deviation
+        let mut peak = (steps / 2, 0.0);
+        for (i, count) in histogram.iter().enumerate() {
+            if *count > peak.1 {
+                peak = (i, *count);
+            }
+        }
+        let (best, count) = peak;
+        if count > 0.0 {
+            best as f64 * TUNING_RESOLUTION - 0.5
+        } else {
+            0.0
+        }
+    }
+
+    /// Computes a chromagram, with one row per pitch class (starting at C) and the same columns as the spectrogram.
+    ///
+    /// The energy of each bin is shared between the two pitch classes nearest to its frequency, after correcting for `tuning` (in fractions of a semitone, estimated with `estimate_tuning` if `None`). Each column is then normalised so that its largest value is 1.
+    ///
+    /// The rows of the chromagram are recorded as the twelve semitones from (tuned) C4, so `bin_to_hz` gives a representative frequency of each pitch class.
+    pub fn chroma(&self, tuning: Option<f64>) -> Spectrogram {
+        let tuning = tuning.unwrap_or_else(|| self.estimate_tuning());
+
+        // The share of each bin's energy given to each pitch class
+        let weights: Vec<Option<(usize, f64)>> = (0..self.height as usize)
+            .map(|bin| {
+                let frequency = self.bin_to_hz(bin);
+                // Bins below C1 are too wide to say anything about pitch
+                if frequency < C1 {
+                    return None;
+                }
+                let class = (hz_to_pitch(frequency) - tuning).rem_euclid(12.0);
+                Some((class.floor() as usize % 12, class - class.floor()))
+            })
+            .collect();
+
+        let mut data = Vec::with_capacity(self.width as usize * 12);
+        for column in self.columns() {
+            let mut chroma = [0.0; 12];
+            for (v, weight) in column.iter().zip(weights.iter()) {
+                if let Some((class, fraction)) = weight {
+                    let energy = v * v;
+                    chroma[*class] += energy * (1.0 - fraction);
+                    chroma[(class + 1) % 12] += energy * fraction;
+                }
+            }
+
+            let max = chroma.iter().cloned().fold(0.0, f64::max);
+            data.extend(chroma.iter().map(|c| if max > 0.0 { c / max } else { 0.0 }));
+        }
+
+        let mut parameters = self.parameters_or_default();
+        parameters.fmin = pitch_to_hz(60.0 + tuning);
+        parameters.fmax = pitch_to_hz(72.0 + tuning);
+        parameters.bins_per_octave = 12;
+        parameters.mel_scale = String::new();
+        parameters.normalisation = "chroma_max".to_string();
+
+        Spectrogram {
+            width: self.width,
+            height: 12,
+            data,
+            schema_version: self.schema_version,
+            parameters: Some(parameters),
+            source: self.source.clone(),
+            created: self.created,
+            ..Default::default()
+        }
+    }
+
+    /// Estimates the key of the audio, from its chromagram. See `Key::estimate`.
+    pub fn estimate_key(&self) -> Option<Key> {
+        Key::estimate(&self.chroma(None))
+    }
+
+    /// The frequency (in Hz) of a position `shift` bins away from bin `bin`, interpolating between bins.
+    fn fractional_bin_to_hz(&self, bin: usize, shift: f64) -> f64 {
+        let centre = self.bin_to_hz(bin);
+        if shift >= 0.0 {
+            centre + shift * (self.bin_to_hz(bin + 1) - centre)
+        } else {
+            centre + shift * (centre - self.bin_to_hz(bin.saturating_sub(1)))
+        }
+    }
+}
+
+/// Whether a key is major or minor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Mode {
+    Major,
+    Minor,
+}
+
+/// A musical key, as estimated by `Key::estimate`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Key {
+    /// The pitch class of the tonic, from 0 (C) to 11 (B). See `PITCH_CLASSES`.
+    pub tonic: usize,
+    pub mode: Mode,
+    /// The correlation (in [-1,1]) between the chroma of the audio and the profile of the key.
+    pub confidence: f64,
+}
+
+impl Key {
+    /// Estimates the key of a chromagram (as computed by `Spectrogram::chroma`) with the Krumhansl-Schmuckler algorithm.
+    ///
+    /// The total chroma of the whole track is correlated with the Krumhansl-Kessler profiles of all 24 major and minor keys, and the best matching key is returned. Returns `None` if the chromagram doesn't have twelve rows, or has no energy.
+    pub fn estimate(chroma: &Spectrogram) -> Option<Key> {
+        if chroma.height != 12 {
+            return None;
+        }
+
+        let mut totals = [0.0; 12];
+        for column in chroma.columns() {
+            totals.iter_mut().zip(column).for_each(|(t, c)| *t += c);
+        }
+
+        let mut best: Option<Key> = None;
+        for tonic in 0..12 {
+            for (mode, profile) in &[(Mode::Major, MAJOR_PROFILE), (Mode::Minor, MINOR_PROFILE)] {
+                // Rotate the profile so that it starts at the tonic
+                let rotated: Vec<f64> = (0..12).map(|c| profile[(c + 12 - tonic) % 12]).collect();
+                let confidence = correlation(&totals[..], &rotated[..])?;
+                if best.is_none_or(|b| confidence > b.confidence) {
+                    best = Some(Key {
+                        tonic,
+                        mode: *mode,
+                        confidence,
+                    });
+                }
+            }
+        }
+        best
+    }
+
+    /// The name of the tonic, e.g. "F#".
+    pub fn tonic_name(&self) -> &'static str {
+        PITCH_CLASSES[self.tonic % 12]
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let mode = match self.mode {
+            Mode::Major => "major",
+            Mode::Minor => "minor",
+        };
+        write!(formatter, "{} {}", self.tonic_name(), mode)
+    }
+}
+
+/// The (fractional) MIDI note number of a frequency.
+fn hz_to_pitch(frequency: f64) -> f64 {
+    12.0 * (frequency / 440.0).log2() + 69.0
+}
+
+fn pitch_to_hz(pitch: f64) -> f64 {
+    440.0 * 2f64.powf((pitch - 69.0) / 12.0)
+}
+
+/// The Pearson correlation of two sequences, or `None` if either is constant.
+fn correlation(a: &[f64], b: &[f64]) -> Option<f64> {
+    let n = a.len() as f64;
+    let (mean_a, mean_b) = (a.iter().sum::<f64>() / n, b.iter().sum::<f64>() / n);
+
+    let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b.iter()) {
+        cov += (x - mean_a) * (y - mean_b);
+        var_a += (x - mean_a) * (x - mean_a);
+        var_b += (y - mean_b) * (y - mean_b);
+    }
+
+    if var_a <= 0.0 || var_b <= 0.0 {
+        return None;
+    }
+    Some(cov / (var_a * var_b).sqrt())
+}
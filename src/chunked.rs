@@ -0,0 +1,286 @@
+/*!
+ * Streaming encoding and decoding of spectrograms as a sequence of chunks.
+ *
+ * A single `Spectrogram` message for a multi-hour recording is one huge allocation, both when it is encoded and when it is decoded. Instead, a spectrogram can be written as a stream of length delimited `SpectrogramChunk` messages: a `SpectrogramHeader`, followed by `ColumnBlock`s that each hold a few columns of the spectrogram, tagged with the index of their first frame. This means that columns can be written as they are computed (e.g. by `stft::streaming::STFT`), and read back one block at a time.
+ *
+ * ```ignore
+ * let mut writer = ChunkWriter::new(file, header, 256)?;
+ * while stft.contains_enough_to_compute() {
+ *     stft.compute_magnitude_column(&mut column[..]);
+ *     writer.write_column(&column[..])?;
+ *     stft.move_to_next_column();
+ * }
+ * writer.finish()?;
+ * ```
+ */
+use super::spectrogram_chunk::Chunk;
+use super::{ColumnBlock, Spectrogram, SpectrogramChunk, SpectrogramHeader};
+
+use std::io::{self, Read, Write};
+
+use prost::Message;
+
+/// The largest chunk (in bytes) that a `ChunkReader` will read. Longer chunks are rejected as corrupt, rather than allocated.
+pub const MAX_CHUNK_LENGTH: u64 = 64 << 20;
+
+/// The most values a block can hold, leaving room for its other fields within `MAX_CHUNK_LENGTH`.
+const MAX_BLOCK_VALUES: u64 = (MAX_CHUNK_LENGTH - 64) / 8;
+
+impl Spectrogram {
+    /// A header describing the spectrogram, without any of its data.
+    pub fn header(&self) -> SpectrogramHeader {
+        SpectrogramHeader {
+            height: self.height,
+            schema_version: self.schema_version,
+            parameters: self.parameters.clone(),
+            source: self.source.clone(),
+            created: self.created,
+        }
+    }
+
+    /// Writes the spectrogram as a chunked stream, with (at most) `block_size` columns per block.
+    ///
+    /// Fails with `InvalidInput` if the spectrogram has no rows, or the blocks would be too large (see `ChunkWriter::new`).
+    pub fn write_chunked<W: Write>(&self, w: W, block_size: usize) -> io::Result<W> {
+        let mut writer = ChunkWriter::new(w, self.header(), block_size)?;
+        for block in self.data.chunks(self.height as usize * block_size) {
+            writer.write_columns(block)?;
+        }
+        writer.finish()
+    }
+
+    /// Reads a complete chunked stream into a single spectrogram.
+    pub fn read_chunked<R: Read>(r: R) -> io::Result<Self> {
+        let reader = ChunkReader::new(r)?;
+        let header = reader.header().clone();
+
+        let mut data = Vec::new();
+        for block in reader {
+            data.extend(block?.data);
+        }
+
+        let width = if header.height == 0 {
+            0
+        } else {
+            data.len() / header.height as usize
+        };
+
+        Ok(Spectrogram {
+            width: width as u32,
+            height: header.height,
+            data,
+            schema_version: header.schema_version,
+            parameters: header.parameters,
+            source: header.source,
+            created: header.created,
+            ..Default::default()
+        })
+    }
+}
+
+/// Writes a spectrogram to a stream, a block of columns at a time.
+pub struct ChunkWriter<W: Write> {
+    writer: W,
+    height: usize,
+    block_size: usize,
+    /// Columns waiting to be written as a block
+    pending: Vec<f64>,
+    /// The index of the first pending column
+    frame: u64,
+}
+
+impl<W: Write> ChunkWriter<W> {
+    /// Starts a chunked stream by writing `header`. Columns passed to `write_column` are buffered, and written in blocks of `block_size`.
+    ///
+    /// Fails with `InvalidInput` if `header.height` or `block_size` is zero, or if a block would be larger than `MAX_CHUNK_LENGTH` bytes.
+    pub fn new(mut writer: W, header: SpectrogramHeader, block_size: usize) -> io::Result<Self> {
+        if header.height == 0 || block_size == 0 {
+            return Err(invalid_input(
+                "chunked streams need a non-zero height and block size",
+            ));
+        }
+        if (header.height as u64).saturating_mul(block_size as u64) > MAX_BLOCK_VALUES {
+            return Err(invalid_input("column blocks are too large"));
+        }
+
+        let height = header.height as usize;
+        write_chunk(&mut writer, Chunk::Header(header))?;
+
+        Ok(ChunkWriter {
+            writer,
+            height,
+            block_size,
+            pending: Vec::with_capacity(height * block_size),
+            frame: 0,
+        })
+    }
+
+    /// The number of columns written (or buffered) so far.
+    pub fn frames(&self) -> u64 {
+        self.frame + (self.pending.len() / self.height) as u64
+    }
+
+    /// Buffers a single column, writing out a block if enough columns have been buffered.
+    ///
+    /// # Panics
+    /// panics unless `column.len()` is the height of the spectrogram
+    pub fn write_column(&mut self, column: &[f64]) -> io::Result<()> {
+        assert_eq!(column.len(), self.height);
+
+        self.pending.extend_from_slice(column);
+        if self.pending.len() >= self.height * self.block_size {
+            self.flush_block()?;
+        }
+        Ok(())
+    }
+
+    /// Writes a (column major) block of columns directly, after any buffered columns.
+    ///
+    /// # Panics
+    /// panics unless `columns.len()` is a multiple of the height of the spectrogram
+    pub fn write_columns(&mut self, columns: &[f64]) -> io::Result<()> {
+        assert_eq!(columns.len() % self.height, 0);
+
+        self.flush_block()?;
+        self.write_block(columns.to_vec())
+    }
+
+    /// Writes any buffered columns, and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush_block()?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn flush_block(&mut self) -> io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let block = std::mem::replace(
+            &mut self.pending,
+            Vec::with_capacity(self.height * self.block_size),
+        );
+        self.write_block(block)
+    }
+
+    fn write_block(&mut self, data: Vec<f64>) -> io::Result<()> {
+        let width = (data.len() / self.height) as u32;
+        if width == 0 {
+            return Ok(());
+        }
+
+        let block = ColumnBlock {
+            start_frame: self.frame,
+            width,
+            data,
+        };
+        self.frame += width as u64;
+        write_chunk(&mut self.writer, Chunk::Columns(block))
+    }
+}
+
+/// Reads a chunked spectrogram stream, a block of columns at a time.
+///
+/// Iterating over the reader yields each `ColumnBlock` in the stream in turn.
+pub struct ChunkReader<R: Read> {
+    reader: R,
+    header: SpectrogramHeader,
+    /// The index of the frame we expect the next block to start at
+    frame: u64,
+}
+
+impl<R: Read> ChunkReader<R> {
+    /// Starts reading a chunked stream, by reading its header.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        match read_chunk(&mut reader)? {
+            Some(Chunk::Header(header)) => Ok(ChunkReader {
+                reader,
+                header,
+                frame: 0,
+            }),
+            _ => Err(invalid_data("chunked stream does not start with a header")),
+        }
+    }
+
+    pub fn header(&self) -> &SpectrogramHeader {
+        &self.header
+    }
+
+    /// Reads the next block of columns, or `None` at the end of the stream.
+    ///
+    /// Fails if blocks are out of order, or inconsistent with the height of the spectrogram.
+    pub fn next_block(&mut self) -> io::Result<Option<ColumnBlock>> {
+        let block = match read_chunk(&mut self.reader)? {
+            None => return Ok(None),
+            Some(Chunk::Columns(block)) => block,
+            Some(Chunk::Header(_)) => return Err(invalid_data("unexpected header in chunked stream")),
+        };
+
+        if block.start_frame != self.frame {
+            return Err(invalid_data("column block is out of order"));
+        }
+        if block.data.len() != block.width as usize * self.header.height as usize {
+            return Err(invalid_data("column block size does not match its dimensions"));
+        }
+
+        self.frame += block.width as u64;
+        Ok(Some(block))
+    }
+}
+
+impl<R: Read> Iterator for ChunkReader<R> {
+    type Item = io::Result<ColumnBlock>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_block().transpose()
+    }
+}
+
+fn write_chunk<W: Write>(w: &mut W, chunk: Chunk) -> io::Result<()> {
+    let message = SpectrogramChunk { chunk: Some(chunk) };
+    let mut buf = Vec::with_capacity(message.encoded_len() + 10);
+    message.encode_length_delimited(&mut buf)?;
+    w.write_all(&buf[..])
+}
+
+/// Reads a length delimited chunk, returning `None` if the stream ends cleanly before it.
+fn read_chunk<R: Read>(r: &mut R) -> io::Result<Option<Chunk>> {
+    // Read the varint length delimiter a byte at a time, as we can't read past the chunk
+    let mut length: u64 = 0;
+    for (i, shift) in (0..64).step_by(7).enumerate() {
+        let mut byte = [0; 1];
+        if r.read(&mut byte)? == 0 {
+            if i == 0 {
+                return Ok(None);
+            }
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "truncated chunk length",
+            ));
+        }
+        length |= ((byte[0] & 0x7f) as u64) << shift;
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+    }
+
+    if length > MAX_CHUNK_LENGTH {
+        return Err(invalid_data("chunk is too long"));
+    }
+    let mut buf = vec![0; length as usize];
+    r.read_exact(&mut buf[..])?;
+
+    let message = SpectrogramChunk::decode(&buf[..])?;
+    message
+        .chunk
+        .map(Some)
+        .ok_or_else(|| invalid_data("empty chunk"))
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn invalid_input(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
@@ -0,0 +1,259 @@
+/*!
+ * The constant-Q transform (CQT), similar to `librosa.cqt`.
+ *
+ * The bins of an STFT are evenly spaced in frequency, so at low frequencies a single bin can cover several semitones, while at high frequencies each semitone is covered by many bins. The bins of a CQT are instead geometrically spaced, with a fixed number of bins per octave, and a filter length that shrinks as the frequency increases, so that every bin has the same frequency resolution relative to its centre frequency (the "Q"). This makes CQT spectrograms a much better fit for key and harmony analysis.
+ *
+ * The transform is computed with the "sparse kernel" method of Brown and Puckette: each frame of audio is transformed with a single (large) FFT, and then multiplied with the precomputed, sparse, spectra of each bin's filter.
+ *
+ * CQT spectrograms are computed with a `SpectrogramBuilder`, and have the same columns (i.e. the same frames) as the STFT spectrogram computed with the same builder. As the rows are log-spaced, the images produced by `as_image_col` and friends have a musical (log) frequency axis. `Spectrogram::as_image_cqt` additionally marks where each octave starts, and `Spectrogram::octave_rows` gives the rows (and frequencies) of those octaves, e.g. for labelling the axis.
+ *
+ * ```ignore
+ * let spectrogram = SpectrogramBuilder::new()
+ *     .cqt(Cqt::default())
+ *     .build(&samples);
+ * // The frequency of middle C
+ * let c4 = spectrogram.bin_to_hz(36);
+ * ```
+ */
+use super::stft::WindowType;
+use super::Spectrogram;
+
+use image::RgbImage;
+
+use std::sync::Arc;
+
+use num::complex::Complex;
+use num::traits::Zero;
+use rayon::prelude::*;
+use rustfft::{FFTplanner, FFT};
+
+/// The frequency (in Hz) of C1, the default lowest bin of a CQT, as in librosa.
+pub const C1: f64 = 32.703_195_662_574_83;
+
+/// The proportion of the energy of each filter's spectrum that is discarded to make the kernel sparse, as in librosa.
+const SPARSITY: f64 = 0.01;
+
+/// The parameters of a constant-Q transform. The default parameters are those of `librosa.cqt`: seven octaves of semitones, starting from C1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cqt {
+    /// The centre frequency (in Hz) of the lowest bin.
+    pub fmin: f64,
+    /// The number of frequency bins.
+    pub n_bins: usize,
+    pub bins_per_octave: usize,
+    /// Scales the length of each filter. Smaller values give better time resolution, but worse frequency resolution.
+    pub filter_scale: f64,
+}
+
+impl Default for Cqt {
+    fn default() -> Self {
+        Cqt {
+            fmin: C1,
+            n_bins: 84,
+            bins_per_octave: 12,
+            filter_scale: 1.0,
+        }
+    }
+}
+
+impl Cqt {
+    /// The ratio of each bin's centre frequency to its bandwidth.
+    pub fn q(&self) -> f64 {
+        self.filter_scale / (2f64.powf(1.0 / self.bins_per_octave as f64) - 1.0)
+    }
+
+    /// The centre frequency (in Hz) of bin `bin`.
+    pub fn frequency(&self, bin: usize) -> f64 {
+        self.fmin * 2f64.powf(bin as f64 / self.bins_per_octave as f64)
+    }
+
+    /// The centre frequencies (in Hz) of every bin.
+    pub fn frequencies(&self) -> Vec<f64> {
+        (0..self.n_bins).map(|bin| self.frequency(bin)).collect()
+    }
+
+    /// The length (in samples) of the filter for a frequency (in Hz).
+    pub fn filter_length(&self, frequency: f64, sample_rate: u32) -> usize {
+        (self.q() * sample_rate as f64 / frequency).ceil() as usize
+    }
+
+    /// Precomputes the kernel of the transform, for audio at `sample_rate`.
+    ///
+    /// # Panics
+    /// panics if there are no bins, or if the highest bin is above the Nyquist frequency
+    pub fn kernel(&self, window_type: WindowType, sample_rate: u32) -> CqtKernel {
+        assert!(self.n_bins > 0 && self.bins_per_octave > 0);
+        assert!(self.fmin > 0.0, "fmin must be positive");
+        assert!(
+            self.frequency(self.n_bins - 1) < sample_rate as f64 / 2.0,
+            "the highest CQT bin is above the Nyquist frequency"
+        );
+
+        let filter_length = self.filter_length(self.fmin, sample_rate);
+        let fft_size = filter_length.next_power_of_two();
+        let fft = FFTplanner::new(false).plan_fft(fft_size);
+
+        let bins = (0..self.n_bins)
+            .map(|bin| {
+                let frequency = self.frequency(bin);
+                let length = self.filter_length(frequency, sample_rate);
+                let window = window_type
+                    .as_window_vec::<f64>(length)
+                    .unwrap_or_else(|| vec![1.0; length]);
+                let window_sum: f64 = window.iter().sum();
+
+                // A windowed complex sinusoid, centred in the FFT frame, normalised so that a (real) sinusoid with amplitude `a` at the centre frequency has a magnitude of `a / 2`.
+                let mut filter = vec![Complex::zero(); fft_size];
+                let start = fft_size / 2 - length / 2;
+                for (n, w) in window.iter().enumerate() {
+                    let phase =
+                        2.0 * std::f64::consts::PI * frequency * (n as f64 - length as f64 / 2.0)
+                            / sample_rate as f64;
+                    filter[start + n] = Complex::from_polar(&(w / window_sum), &phase);
+                }
+
+                let mut spectrum = vec![Complex::zero(); fft_size];
+                fft.process(&mut filter, &mut spectrum);
+                sparsify(&spectrum[..], fft_size)
+            })
+            .collect();
+
+        CqtKernel {
+            fft,
+            fft_size,
+            filter_length,
+            bins,
+        }
+    }
+}
+
+/// A precomputed constant-Q transform kernel, as returned by `Cqt::kernel`.
+pub struct CqtKernel {
+    fft: Arc<dyn FFT<f64>>,
+    fft_size: usize,
+    filter_length: usize,
+    /// The non-zero elements of the (conjugated, and scaled) spectrum of each bin's filter
+    bins: Vec<Vec<(usize, Complex<f64>)>>,
+}
+
+impl CqtKernel {
+    /// The size of the FFT used for each frame.
+    pub fn fft_size(&self) -> usize {
+        self.fft_size
+    }
+
+    /// The length (in samples) of the longest filter, i.e. that of the lowest bin.
+    pub fn filter_length(&self) -> usize {
+        self.filter_length
+    }
+
+    /// The number of frequency bins.
+    pub fn height(&self) -> usize {
+        self.bins.len()
+    }
+
+    /// Computes the CQT magnitudes of a single frame, centred on sample `centre`. The audio is treated as zero outside of `samples`.
+    pub fn compute_column(&self, samples: &[f64], centre: usize) -> Vec<f64> {
+        let half = self.fft_size / 2;
+        let mut input: Vec<Complex<f64>> = (0..self.fft_size)
+            .map(|i| {
+                (centre + i)
+                    .checked_sub(half)
+                    .and_then(|ix| samples.get(ix))
+                    .map_or(Complex::zero(), |s| Complex::new(*s, 0.0))
+            })
+            .collect();
+
+        let mut spectrum = vec![Complex::zero(); self.fft_size];
+        self.fft.process(&mut input, &mut spectrum);
+
+        self.bins
+            .iter()
+            .map(|kernel| {
+                kernel
+                    .iter()
+                    .fold(Complex::zero(), |sum: Complex<f64>, (j, k)| {
+                        sum + spectrum[*j] * k
+                    })
+                    .norm()
+            })
+            .collect()
+    }
+
+    /// Computes the (column major) CQT magnitudes of some audio, with the same frames as `stft::inplace::STFT::par_iter_stft`, i.e. frames of `window_size` samples, every `step_size` samples.
+    pub fn compute(&self, samples: &[f64], window_size: usize, step_size: usize) -> Vec<f64> {
+        let frames = if samples.len() < window_size {
+            0
+        } else {
+            (samples.len() - window_size) / step_size + 1
+        };
+
+        (0..frames)
+            .into_par_iter()
+            .map(|frame| self.compute_column(samples, frame * step_size + window_size / 2))
+            .flatten()
+            .collect()
+    }
+}
+
+impl Spectrogram {
+    /// The rows at which each octave of a constant-Q spectrogram starts, i.e. every `bins_per_octave` rows from `fmin`, along with their frequencies (in Hz).
+    ///
+    /// Empty unless the spectrogram's `parameters` record a `bins_per_octave`, as STFT spectrograms have no octave structure.
+    pub fn octave_rows(&self) -> Vec<(u32, f64)> {
+        match self.parameters {
+            Some(ref parameters) if parameters.bins_per_octave > 0 => (0..self.height)
+                .step_by(parameters.bins_per_octave as usize)
+                .map(|row| (row, self.bin_to_hz(row as usize)))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Generates a colour image of a constant-Q spectrogram, as `as_image_col`, with a lightened line along the start of every octave above the first (see `octave_rows`).
+    ///
+    /// STFT spectrograms have no octaves to mark, so are rendered exactly as by `as_image_col`.
+    pub fn as_image_cqt(&self) -> RgbImage {
+        let mut img = self.as_image_col();
+
+        for (row, _) in self.octave_rows().into_iter().skip(1) {
+            // The lowest bin is at the bottom of the image
+            let y = self.height - row - 1;
+            for x in 0..self.width {
+                let pixel = img.get_pixel_mut(x, y);
+                for channel in pixel.0.iter_mut() {
+                    *channel = ((*channel as u16 + 255) / 2) as u8;
+                }
+            }
+        }
+
+        img
+    }
+}
+
+/// Keeps the largest elements of a filter's spectrum, and prepares them for correlation with the spectrum of a frame.
+fn sparsify(spectrum: &[Complex<f64>], fft_size: usize) -> Vec<(usize, Complex<f64>)> {
+    let mut magnitudes: Vec<f64> = spectrum.iter().map(|c| c.norm()).collect();
+    magnitudes.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+    // Find the smallest magnitude to keep, discarding (at most) SPARSITY of the total
+    let total: f64 = magnitudes.iter().sum();
+    let mut discarded = 0.0;
+    let mut threshold = 0.0;
+    for m in magnitudes {
+        discarded += m;
+        if discarded > SPARSITY * total {
+            threshold = m;
+            break;
+        }
+    }
+
+    // By Parseval's theorem, correlating with the filter is the same as summing the product of the frame's spectrum with the conjugate of the filter's spectrum, divided by the FFT size.
+    spectrum
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| c.norm() >= threshold)
+        .map(|(j, c)| (j, c.conj() / fft_size as f64))
+        .collect()
+}
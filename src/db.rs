@@ -0,0 +1,143 @@
+/*!
+ * Conversions between amplitude, power and decibels, equivalent to librosa's `amplitude_to_db`, `power_to_db`, `db_to_amplitude` and `db_to_power`.
+ *
+ * All conversions operate in place on a buffer of spectrogram values. `from_buffer` converts to dB relative to the loudest value in the track (`Reference::Max`), which makes every track peak at 0dB. To compare loudness across tracks, use an absolute reference instead:
+ *
+ * ```ignore
+ * // dB relative to a full scale sine wave, rather than the peak of the track
+ * db::amplitude_to_db(&mut magnitudes[..], &Reference::Value(1.0), AMIN, None);
+ * ```
+ */
+use rayon::prelude::*;
+
+/// The default `amin` of `power_to_db`, as in librosa. The default for `amplitude_to_db` is `AMIN`.
+pub const POWER_AMIN: f64 = 1e-10;
+
+/// The reference value that dB values are computed relative to (the `ref` argument of librosa's conversions).
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Reference {
+    /// A fixed reference, for comparisons across spectrograms.
+    Value(f64),
+    /// The maximum of the (absolute) input values, i.e. `ref=np.max`.
+    Max,
+    /// The median of the (absolute) input values, i.e. `ref=np.median`.
+    Median,
+    /// A function computing the reference from the (absolute) input values. Can't be serialised.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Function(fn(&[f64]) -> f64),
+}
+
+impl Reference {
+    /// Computes the reference value for some (absolute) input values.
+    pub fn compute(&self, values: &[f64]) -> f64 {
+        match self {
+            Reference::Value(v) => v.abs(),
+            Reference::Max => values.iter().fold(f64::MIN, |m, x| m.max(*x)),
+            Reference::Median => median(values),
+            Reference::Function(f) => f(values),
+        }
+    }
+}
+
+impl std::fmt::Display for Reference {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Reference::Value(v) => write!(formatter, "{}", v),
+            Reference::Max => write!(formatter, "max"),
+            Reference::Median => write!(formatter, "median"),
+            Reference::Function(_) => write!(formatter, "function"),
+        }
+    }
+}
+
+/// Converts amplitudes to dB, as `librosa.amplitude_to_db`.
+///
+/// The reference is computed from the absolute amplitudes, and values below `amin` are treated as `amin`. If `top_db` is given, the output is clipped to at most `top_db` below its peak.
+///
+/// # Panics
+/// panics if `amin` is not positive, or `top_db` is negative
+pub fn amplitude_to_db(s: &mut [f64], reference: &Reference, amin: f64, top_db: Option<f64>) {
+    s.iter_mut().for_each(|v| *v = v.abs());
+    let ref_value = reference.compute(s);
+
+    // Don't forget to elementwise square S first!
+    s.iter_mut().for_each(|v| *v = *v * *v);
+    power_to_db(
+        s,
+        &Reference::Value(ref_value * ref_value),
+        amin * amin,
+        top_db,
+    );
+}
+
+/// Converts powers to dB, as `librosa.power_to_db`, i.e. computes `10 * log10(max(s, amin) / max(ref, amin))`.
+///
+/// If `top_db` is given, the output is clipped to at most `top_db` below its peak.
+///
+/// # Panics
+/// panics if `amin` is not positive, or `top_db` is negative
+pub fn power_to_db(s: &mut [f64], reference: &Reference, amin: f64, top_db: Option<f64>) {
+    if amin <= 0.0 {
+        panic!("amin must be > 0");
+    }
+    if top_db.is_some_and(|t| t < 0.0) {
+        panic!("top_db must be >= 0");
+    }
+
+    let ref_value = match reference {
+        Reference::Value(v) => v.abs(),
+        _ => {
+            let magnitudes: Vec<f64> = s.iter().map(|v| v.abs()).collect();
+            reference.compute(&magnitudes[..])
+        }
+    };
+
+    // Pull this value out of the inner loop
+    let tammrf = 10.0 * amin.max(ref_value).log10();
+
+    s.par_iter_mut().for_each(|v| {
+        // Calculate log_spec
+        *v = (10.0 * amin.max(*v).log10()) - tammrf;
+    });
+
+    if let Some(top_db) = top_db {
+        let max: f64 = s
+            .iter()
+            .fold(f64::MIN, |m, x| if *x > m { *x } else { m });
+
+        // Second loop after we have the max
+        s.iter_mut().for_each(|v| *v = v.max(max - top_db));
+    }
+}
+
+/// Converts dB back to powers, as `librosa.db_to_power`, i.e. computes `reference * 10^(s / 10)`.
+///
+/// Any clipping by `top_db` can't be undone, and the reference has to be the value that was used by `power_to_db`.
+pub fn db_to_power(s: &mut [f64], reference: f64) {
+    s.par_iter_mut()
+        .for_each(|v| *v = reference * 10f64.powf(0.1 * *v));
+}
+
+/// Converts dB back to amplitudes, as `librosa.db_to_amplitude`.
+///
+/// Any clipping by `top_db` can't be undone, and the reference has to be the value that was used by `amplitude_to_db`.
+pub fn db_to_amplitude(s: &mut [f64], reference: f64) {
+    db_to_power(s, reference * reference);
+    s.iter_mut().for_each(|v| *v = v.sqrt());
+}
+
+fn median(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    }
+}
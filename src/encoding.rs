@@ -0,0 +1,214 @@
+/*!
+ * Compact storage encodings for protobuf spectrograms.
+ *
+ * By default, a `Spectrogram` message stores its data as `repeated double`, which costs 8 bytes per cell. The methods in this module re-encode the data into one of the more compact representations described by `Encoding` before it is written, and expand it back into `data` after it is read, so that the rest of tizol only ever deals with double precision data.
+ *
+ * ```ignore
+ * let compact = spectrogram.with_encoding(Encoding::Quantised8)?;
+ * compact.encode(&mut buf)?;
+ *
+ * // Decode, and expand back to `Encoding::Double`
+ * let restored = Spectrogram::decode_expanded(&buf[..])?;
+ * ```
+ */
+use super::{Encoding, Spectrogram};
+
+use bytes::IntoBuf;
+use prost::{DecodeError, Message};
+
+impl Spectrogram {
+    /// Returns a copy of the spectrogram, with its data stored using `encoding`.
+    ///
+    /// Apart from `Encoding::Double`, all encodings are lossy: `Float` rounds to single precision, and the quantised encodings round each value to one of 256 (or 65536) evenly spaced levels between the minimum and maximum of the data.
+    ///
+    /// If the spectrogram is already compactly encoded, it is expanded first.
+    pub fn with_encoding(&self, encoding: Encoding) -> Result<Self, DecodeError> {
+        let mut expanded = self.clone();
+        expanded.expand()?;
+        let data = std::mem::take(&mut expanded.data);
+
+        match encoding {
+            Encoding::Double => expanded.data = data,
+            Encoding::Float => expanded.data_f32 = data.iter().map(|v| *v as f32).collect(),
+            Encoding::Quantised8 => {
+                let (offset, scale, levels) = quantise(&data[..], u8::MAX as f64);
+                expanded.offset = offset;
+                expanded.scale = scale;
+                expanded.data_quantised = levels.iter().map(|q| *q as u8).collect();
+            }
+            Encoding::Quantised16 => {
+                let (offset, scale, levels) = quantise(&data[..], u16::MAX as f64);
+                expanded.offset = offset;
+                expanded.scale = scale;
+                expanded.data_quantised = levels
+                    .iter()
+                    .flat_map(|q| (*q as u16).to_le_bytes().to_vec())
+                    .collect();
+            }
+            Encoding::DeltaRle16 => {
+                let (offset, scale, levels) = quantise(&data[..], u16::MAX as f64);
+                expanded.offset = offset;
+                expanded.scale = scale;
+                expanded.data_quantised = delta_rle_encode(&levels[..]);
+            }
+        }
+
+        expanded.set_encoding(encoding);
+        Ok(expanded)
+    }
+
+    /// Decodes any compactly encoded data back into `data`, leaving the spectrogram with `Encoding::Double`.
+    ///
+    /// Fails if the compact data is inconsistent with the dimensions of the spectrogram.
+    pub fn expand(&mut self) -> Result<(), DecodeError> {
+        let cells = self.width as usize * self.height as usize;
+        let (offset, scale) = (self.offset, self.scale);
+        let dequantise = |q: u32| offset + q as f64 * scale;
+
+        let data: Vec<f64> = match self.encoding() {
+            Encoding::Double => return Ok(()),
+            Encoding::Float => self.data_f32.iter().map(|v| *v as f64).collect(),
+            Encoding::Quantised8 => self
+                .data_quantised
+                .iter()
+                .map(|q| dequantise(*q as u32))
+                .collect(),
+            Encoding::Quantised16 => {
+                if !self.data_quantised.len().is_multiple_of(2) {
+                    return Err(DecodeError::new("16-bit quantised data has an odd length"));
+                }
+                self.data_quantised
+                    .chunks(2)
+                    .map(|q| dequantise(u16::from_le_bytes([q[0], q[1]]) as u32))
+                    .collect()
+            }
+            Encoding::DeltaRle16 => delta_rle_decode(&self.data_quantised[..], cells)?
+                .into_iter()
+                .map(dequantise)
+                .collect(),
+        };
+
+        if data.len() != cells {
+            return Err(DecodeError::new(
+                "compact spectrogram data does not match its dimensions",
+            ));
+        }
+
+        self.data = data;
+        self.data_f32 = Vec::new();
+        self.data_quantised = Vec::new();
+        self.scale = 0.0;
+        self.offset = 0.0;
+        self.set_encoding(Encoding::Double);
+        Ok(())
+    }
+
+    /// Decodes a spectrogram written with any encoding, expanding its data back into `data`.
+    pub fn decode_expanded<B: IntoBuf>(buf: B) -> Result<Self, DecodeError> {
+        let mut spectrogram = Self::decode(buf)?;
+        spectrogram.expand()?;
+        Ok(spectrogram)
+    }
+}
+
+/// Quantises data to integers in `[0, max_level]`, returning the offset and scale that map them back.
+fn quantise(data: &[f64], max_level: f64) -> (f64, f64, Vec<u32>) {
+    let (min, max) = data
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(mi, ma), x| {
+            (mi.min(*x), ma.max(*x))
+        });
+
+    if data.is_empty() || max <= min {
+        // Constant (or empty) data only needs the offset.
+        let offset = if data.is_empty() { 0.0 } else { min };
+        return (offset, 0.0, vec![0; data.len()]);
+    }
+
+    let scale = (max - min) / max_level;
+    let levels = data
+        .iter()
+        .map(|v| ((v - min) / scale).round().max(0.0).min(max_level) as u32)
+        .collect();
+    (min, scale, levels)
+}
+
+/// Delta encodes a sequence of levels, and then run-length encodes the deltas.
+fn delta_rle_encode(levels: &[u32]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut previous: i64 = 0;
+    let mut run: Option<(u64, i64)> = None;
+
+    for level in levels {
+        let delta = *level as i64 - previous;
+        previous = *level as i64;
+
+        run = match run {
+            Some((length, d)) if d == delta => Some((length + 1, d)),
+            Some((length, d)) => {
+                write_run(&mut out, length, d);
+                Some((1, delta))
+            }
+            None => Some((1, delta)),
+        };
+    }
+    if let Some((length, d)) = run {
+        write_run(&mut out, length, d);
+    }
+    out
+}
+
+/// Reverses `delta_rle_encode`, expecting exactly `cells` levels.
+fn delta_rle_decode(mut bytes: &[u8], cells: usize) -> Result<Vec<u32>, DecodeError> {
+    let mut levels = Vec::with_capacity(cells);
+    let mut previous: i64 = 0;
+
+    while !bytes.is_empty() {
+        let length = read_varint(&mut bytes)?;
+        let encoded = read_varint(&mut bytes)?;
+        // Undo the zigzag encoding
+        let delta = (encoded >> 1) as i64 ^ -((encoded & 1) as i64);
+
+        if levels.len() as u64 + length > cells as u64 {
+            return Err(DecodeError::new("run-length encoded data is too long"));
+        }
+        for _ in 0..length {
+            previous += delta;
+            if previous < 0 || previous > u16::MAX as i64 {
+                return Err(DecodeError::new("delta encoded level is out of range"));
+            }
+            levels.push(previous as u32);
+        }
+    }
+
+    Ok(levels)
+}
+
+fn write_run(out: &mut Vec<u8>, length: u64, delta: i64) {
+    write_varint(out, length);
+    // Zigzag encode the delta, so that small negative deltas stay small
+    write_varint(out, ((delta << 1) ^ (delta >> 63)) as u64);
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Result<u64, DecodeError> {
+    let mut value: u64 = 0;
+    for shift in (0..64).step_by(7) {
+        let (byte, rest) = bytes
+            .split_first()
+            .ok_or_else(|| DecodeError::new("truncated varint"))?;
+        *bytes = rest;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(DecodeError::new("varint is too long"))
+}
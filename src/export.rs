@@ -0,0 +1,588 @@
+/*!
+ * Lossless (or near-lossless) file export for spectrogram images.
+ *
+ * The `image` crate can only save 8-bit images, which quantises away most of the detail in quiet passages. This module writes the higher precision images produced by `Spectrogram::as_image_bw16*` and `Spectrogram::as_image_f32_raw` directly, as 16-bit grayscale PNGs and 32-bit floating point TIFFs respectively.
+ *
+ * It also supports writing grayscale PNGs annotated with an `ImageMetadata` block (stored as PNG text chunks) that describes how the spectrogram was produced, and reading them back into a `Spectrogram`.
+ */
+use super::stft::WindowType;
+use super::{content_hash, GrayImage16, GrayImageF32, Parameters, Source, Spectrogram};
+use super::{SAMPLE_RATE, STEP_SIZE, TOP_DB, WINDOW_SIZE};
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
+use std::path::Path;
+
+extern crate png;
+
+extern crate tiff;
+use tiff::decoder::ifd::Tag;
+use tiff::decoder::PhotometricInterpretation;
+use tiff::encoder::colortype::ColorType;
+use tiff::encoder::TiffEncoder;
+
+/// The value of the tiff `SampleFormat` tag for IEEE floating point samples.
+const TIFF_SAMPLE_FORMAT_IEEEFP: u16 = 3;
+
+/// The PNG file signature, which precedes the first chunk.
+const PNG_SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+/// Prefix for the keywords of the text chunks written by tizol.
+const KEY_PREFIX: &str = "tizol:";
+
+/// Writes a 16-bit grayscale image to a PNG file at `path`.
+pub fn save_png16<P: AsRef<Path>>(img: &GrayImage16, path: P) -> io::Result<()> {
+    let file = File::create(path)?;
+    write_png16(img, BufWriter::new(file))
+}
+
+/// Writes a 16-bit grayscale image as a PNG to an arbitrary writer.
+pub fn write_png16<W: Write>(img: &GrayImage16, w: W) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(w, img.width(), img.height());
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Sixteen);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&png16_bytes(img)[..])?;
+    Ok(())
+}
+
+/// Writes a floating point grayscale image to a single channel, 32-bit float TIFF file at `path`.
+pub fn save_tiff_f32<P: AsRef<Path>>(img: &GrayImageF32, path: P) -> io::Result<()> {
+    let file = File::create(path)?;
+    write_tiff_f32(img, BufWriter::new(file))
+}
+
+/// Writes a floating point grayscale image as a 32-bit float TIFF to an arbitrary (seekable) writer.
+pub fn write_tiff_f32<W: Write + Seek>(img: &GrayImageF32, w: W) -> io::Result<()> {
+    // The tiff crate has no notion of floating point samples, so we write the raw bits of each
+    // sample as a u32, and mark the samples as floating point with the `SampleFormat` tag.
+    let bits: Vec<u32> = img.iter().map(|v| v.to_bits()).collect();
+
+    let mut encoder = TiffEncoder::new(w).map_err(tiff_error)?;
+    let mut image = encoder
+        .new_image::<Gray32Float>(img.width(), img.height())
+        .map_err(tiff_error)?;
+    image
+        .encoder()
+        .write_tag(Tag::Unknown(339), TIFF_SAMPLE_FORMAT_IEEEFP);
+
+    let mut idx = 0;
+    while image.next_strip_sample_count() > 0 {
+        let sample_count = image.next_strip_sample_count() as usize;
+        image
+            .write_strip(&bits[idx..idx + sample_count])
+            .map_err(tiff_error)?;
+        idx += sample_count;
+    }
+    image.finish().map_err(tiff_error)
+}
+
+/// Single channel, 32-bit samples. Only meaningful with the `SampleFormat` tag set.
+struct Gray32Float;
+impl ColorType for Gray32Float {
+    type Inner = u32;
+    const TIFF_VALUE: PhotometricInterpretation = PhotometricInterpretation::BlackIsZero;
+    const BITS_PER_SAMPLE: &'static [u16] = &[32];
+}
+
+fn tiff_error(e: tiff::TiffError) -> io::Error {
+    io::Error::other(e.to_string())
+}
+
+/// How a spectrogram is laid out in an image.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Orientation {
+    /// Time on the x axis, frequency on the y axis, as produced by `as_image_bw`.
+    Image,
+    /// Frequency on the x axis, time on the y axis, as produced by `as_image_bw_raw`.
+    Raw,
+}
+
+impl std::fmt::Display for Orientation {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Orientation::Image => write!(formatter, "image"),
+            Orientation::Raw => write!(formatter, "raw"),
+        }
+    }
+}
+
+impl std::str::FromStr for Orientation {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &s.to_lowercase()[..] {
+            "image" => Ok(Orientation::Image),
+            "raw" => Ok(Orientation::Raw),
+            _ => Err("no match"),
+        }
+    }
+}
+
+/// The sample depth of an exported grayscale PNG.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BitDepth {
+    Eight,
+    Sixteen,
+}
+
+/// A description of how a spectrogram image was produced, stored alongside the image data in PNG text chunks.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ImageMetadata {
+    /// Layout of the spectrogram in the image
+    pub orientation: Orientation,
+    /// Number of frames (columns) in the spectrogram
+    pub width: u32,
+    /// Number of frequency bins (rows) in the spectrogram
+    pub height: u32,
+    pub window_type: WindowType,
+    pub window_size: u32,
+    pub step_size: u32,
+    pub sample_rate: u32,
+    /// The dB values (relative to the spectrogram's reference) that pixel values of 0 and 1 (respectively) correspond to
+    pub db_range: (f64, f64),
+    /// Name of the audio file that the spectrogram was computed from, if known
+    pub source: Option<String>,
+    /// Hex encoded SHA-256 hash of the source audio file, if known
+    pub source_hash: Option<String>,
+    /// Version of tizol that produced the image
+    pub version: String,
+}
+
+impl ImageMetadata {
+    /// Creates metadata for a spectrogram, to be rendered with the given orientation.
+    ///
+    /// The STFT parameters and source are taken from the spectrogram's `parameters` and `source` where present, and otherwise default to those used by `Spectrogram::from_buffer`.
+    pub fn new(spectrogram: &Spectrogram, orientation: Orientation) -> Self {
+        let mut metadata = ImageMetadata {
+            orientation,
+            width: spectrogram.width,
+            height: spectrogram.height,
+            window_type: WindowType::Hanning,
+            window_size: WINDOW_SIZE as u32,
+            step_size: STEP_SIZE as u32,
+            sample_rate: SAMPLE_RATE,
+            db_range: db_range(spectrogram),
+            source: None,
+            source_hash: None,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        };
+
+        if let Some(ref parameters) = spectrogram.parameters {
+            if let Ok(window_type) = parameters.window.parse() {
+                metadata.window_type = window_type;
+            }
+            metadata.window_size = parameters.window_size;
+            metadata.step_size = parameters.step_size;
+            metadata.sample_rate = parameters.sample_rate;
+        }
+
+        if let Some(ref source) = spectrogram.source {
+            if !source.path.is_empty() {
+                metadata.source = Path::new(&source.path)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned());
+            }
+            if !source.content_hash.is_empty() {
+                metadata.source_hash = Some(source.content_hash.clone());
+            }
+        }
+
+        metadata
+    }
+
+    /// Records the audio file at `path` as the source of the spectrogram, hashing its contents.
+    pub fn with_source<P: AsRef<Path>>(mut self, path: P) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        File::open(&path)?.read_to_end(&mut bytes)?;
+
+        self.source = path
+            .as_ref()
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned());
+        self.source_hash = Some(content_hash(&bytes[..]));
+        Ok(self)
+    }
+
+    /// The spectrogram parameters that can be recovered from the metadata.
+    fn parameters(&self) -> Parameters {
+        Parameters {
+            sample_rate: self.sample_rate,
+            window: self.window_type.to_string(),
+            window_size: self.window_size,
+            step_size: self.step_size,
+            fft_size: self.window_size,
+            top_db: -self.db_range.0,
+            ..Default::default()
+        }
+    }
+
+    /// The description of the source audio that can be recovered from the metadata.
+    fn source(&self) -> Source {
+        Source {
+            path: self.source.clone().unwrap_or_default(),
+            content_hash: self.source_hash.clone().unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    /// Converts the metadata into (keyword, text) pairs.
+    fn to_text(&self) -> Vec<(String, String)> {
+        let mut text = vec![
+            ("version", self.version.clone()),
+            ("orientation", self.orientation.to_string()),
+            ("width", self.width.to_string()),
+            ("height", self.height.to_string()),
+            ("window_type", self.window_type.to_string()),
+            ("window_size", self.window_size.to_string()),
+            ("step_size", self.step_size.to_string()),
+            ("sample_rate", self.sample_rate.to_string()),
+            ("db_min", self.db_range.0.to_string()),
+            ("db_max", self.db_range.1.to_string()),
+        ];
+        if let Some(ref source) = self.source {
+            text.push(("source", source.clone()));
+        }
+        if let Some(ref hash) = self.source_hash {
+            text.push(("source_sha256", hash.clone()));
+        }
+
+        text.into_iter()
+            .map(|(k, v)| (format!("{}{}", KEY_PREFIX, k), v))
+            .collect()
+    }
+
+    /// Rebuilds metadata from (keyword, text) pairs, ignoring any keywords not written by tizol.
+    ///
+    /// Returns `None` if any required field is missing or malformed.
+    fn from_text(text: &[(String, String)]) -> Option<Self> {
+        let get = |key: &str| -> Option<&str> {
+            text.iter()
+                .find(|(k, _)| k.starts_with(KEY_PREFIX) && &k[KEY_PREFIX.len()..] == key)
+                .map(|(_, v)| &v[..])
+        };
+
+        Some(ImageMetadata {
+            orientation: get("orientation")?.parse().ok()?,
+            width: get("width")?.parse().ok()?,
+            height: get("height")?.parse().ok()?,
+            window_type: get("window_type")?.parse().ok()?,
+            window_size: get("window_size")?.parse().ok()?,
+            step_size: get("step_size")?.parse().ok()?,
+            sample_rate: get("sample_rate")?.parse().ok()?,
+            db_range: (get("db_min")?.parse().ok()?, get("db_max")?.parse().ok()?),
+            source: get("source").map(String::from),
+            source_hash: get("source_sha256").map(String::from),
+            version: get("version")?.to_string(),
+        })
+    }
+}
+
+impl Spectrogram {
+    /// Saves the spectrogram as a grayscale PNG at `path`, with `metadata` embedded in text chunks.
+    ///
+    /// The image is rendered using `metadata.orientation`.
+    pub fn save_png_with_metadata<P: AsRef<Path>>(
+        &self,
+        metadata: &ImageMetadata,
+        depth: BitDepth,
+        path: P,
+    ) -> io::Result<()> {
+        let file = File::create(path)?;
+        self.write_png_with_metadata(metadata, depth, BufWriter::new(file))
+    }
+
+    /// Writes the spectrogram as a grayscale PNG with embedded metadata to an arbitrary writer.
+    ///
+    /// If `metadata.db_range` differs from the range the spectrogram covers, the spectrogram is rescaled to it first, saturating levels outside of it.
+    pub fn write_png_with_metadata<W: Write>(
+        &self,
+        metadata: &ImageMetadata,
+        depth: BitDepth,
+        w: W,
+    ) -> io::Result<()> {
+        let rescaled;
+        let spectrogram = if metadata.db_range == db_range(self) {
+            self
+        } else {
+            let mut copy = self.clone();
+            rescale(&mut copy.data[..], db_range(self), metadata.db_range);
+            rescaled = copy;
+            &rescaled
+        };
+
+        let (width, height, bytes) = match (depth, metadata.orientation) {
+            (BitDepth::Eight, Orientation::Image) => {
+                let img = spectrogram.as_image_bw();
+                (img.width(), img.height(), img.into_raw())
+            }
+            (BitDepth::Eight, Orientation::Raw) => {
+                let img = spectrogram.as_image_bw_raw();
+                (img.width(), img.height(), img.into_raw())
+            }
+            (BitDepth::Sixteen, Orientation::Image) => {
+                let img = spectrogram.as_image_bw16();
+                (img.width(), img.height(), png16_bytes(&img))
+            }
+            (BitDepth::Sixteen, Orientation::Raw) => {
+                let img = spectrogram.as_image_bw16_raw();
+                (img.width(), img.height(), png16_bytes(&img))
+            }
+        };
+
+        let mut encoder = png::Encoder::new(w, width, height);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(match depth {
+            BitDepth::Eight => png::BitDepth::Eight,
+            BitDepth::Sixteen => png::BitDepth::Sixteen,
+        });
+        let mut writer = encoder.write_header()?;
+
+        for (key, value) in metadata.to_text() {
+            if value.chars().all(|c| (c as u32) < 256) {
+                writer.write_chunk(*b"tEXt", &text_chunk(&key, &value)[..])?;
+            } else {
+                writer.write_chunk(*b"iTXt", &itext_chunk(&key, &value)[..])?;
+            }
+        }
+
+        writer.write_image_data(&bytes[..])?;
+        Ok(())
+    }
+
+    /// Loads a spectrogram and its metadata from a PNG at `path` written by `save_png_with_metadata`.
+    pub fn load_png_with_metadata<P: AsRef<Path>>(path: P) -> io::Result<(Self, ImageMetadata)> {
+        let file = File::open(path)?;
+        Self::read_png_with_metadata(BufReader::new(file))
+    }
+
+    /// Reads a spectrogram and its metadata from a PNG written by `write_png_with_metadata`.
+    ///
+    /// Fails with `InvalidData` if the PNG is not an 8 or 16-bit grayscale image, or carries no (or incomplete) tizol metadata.
+    pub fn read_png_with_metadata<R: Read>(r: R) -> io::Result<(Self, ImageMetadata)> {
+        match Self::read_image(r, Orientation::Raw)? {
+            (spectrogram, Some(metadata)) => Ok((spectrogram, metadata)),
+            (_, None) => Err(invalid_data("PNG does not contain tizol metadata")),
+        }
+    }
+
+    /// Loads a spectrogram from a grayscale PNG at `path`, as written by this or any earlier version of tizol.
+    ///
+    /// See `read_image` for details.
+    pub fn load_image<P: AsRef<Path>>(
+        path: P,
+        orientation: Orientation,
+    ) -> io::Result<(Self, Option<ImageMetadata>)> {
+        let file = File::open(path)?;
+        Self::read_image(BufReader::new(file), orientation)
+    }
+
+    /// Reads a spectrogram from a grayscale PNG, as written by this or any earlier version of tizol.
+    ///
+    /// If the PNG carries tizol metadata, the orientation and dimensions stored in it are used (and `orientation` is ignored), and pixels are scaled back using the full range of the image's bit depth and the stored `db_range`. The result is scaled so that 1 corresponds to 0 dB, with a `top_db` of `-db_range.0`. Otherwise, the image is assumed to be an 8-bit image saved from `as_image_bw` or `as_image_bw_raw` by an earlier version of tizol, with the given `orientation`, and is scaled back as described in `Spectrogram::from_legacy_image`.
+    pub fn read_image<R: Read>(
+        mut r: R,
+        orientation: Orientation,
+    ) -> io::Result<(Self, Option<ImageMetadata>)> {
+        let mut bytes = Vec::new();
+        r.read_to_end(&mut bytes)?;
+
+        let metadata = ImageMetadata::from_text(&read_text_chunks(&bytes[..])?[..]);
+
+        let mut decoder = png::Decoder::new(&bytes[..]);
+        decoder.set_transformations(png::Transformations::IDENTITY);
+        let (info, mut reader) = decoder.read_info().map_err(png_error)?;
+        if info.color_type != png::ColorType::Grayscale {
+            return Err(invalid_data("PNG is not a grayscale image"));
+        }
+
+        let mut pixels = vec![0; info.buffer_size()];
+        reader.next_frame(&mut pixels).map_err(png_error)?;
+
+        let mut samples: Vec<f64> = match (info.bit_depth, &metadata) {
+            (png::BitDepth::Eight, Some(_)) => pixels
+                .iter()
+                .map(|p| Spectrogram::dequantise_u8(*p))
+                .collect(),
+            (png::BitDepth::Eight, None) => pixels
+                .iter()
+                .map(|p| Spectrogram::dequantise_legacy_u8(*p))
+                .collect(),
+            (png::BitDepth::Sixteen, _) => pixels
+                .chunks(2)
+                .map(|p| Spectrogram::dequantise_u16(u16::from_be_bytes([p[0], p[1]])))
+                .collect(),
+            _ => return Err(invalid_data("unsupported PNG bit depth")),
+        };
+
+        if let Some(ref metadata) = metadata {
+            rescale(
+                &mut samples[..],
+                metadata.db_range,
+                (metadata.db_range.0, 0.0),
+            );
+        }
+
+        let orientation = metadata.as_ref().map_or(orientation, |m| m.orientation);
+        let mut spectrogram = spectrogram_from_samples(samples, info.width, info.height, orientation)
+            .ok_or_else(|| invalid_data("image dimensions do not match PNG data"))?;
+
+        if let Some(ref metadata) = metadata {
+            if (spectrogram.width, spectrogram.height) != (metadata.width, metadata.height) {
+                return Err(invalid_data("image dimensions do not match metadata"));
+            }
+            spectrogram.parameters = Some(metadata.parameters());
+            spectrogram.source = Some(metadata.source());
+        }
+
+        Ok((spectrogram, metadata))
+    }
+}
+
+/// The range of dB levels that the values of a spectrogram span, from 0 to 1.
+fn db_range(spectrogram: &Spectrogram) -> (f64, f64) {
+    match spectrogram.parameters {
+        Some(ref parameters) if parameters.top_db > 0.0 => (-parameters.top_db, 0.0),
+        _ => (-TOP_DB, 0.0),
+    }
+}
+
+/// Linearly maps samples spanning the dB range `from` onto the dB range `to`, so that each sample keeps its level.
+fn rescale(samples: &mut [f64], from: (f64, f64), to: (f64, f64)) {
+    for v in samples.iter_mut() {
+        let db = from.0 + *v * (from.1 - from.0);
+        *v = (db - to.0) / (to.1 - to.0);
+    }
+}
+
+/// Rebuilds a spectrogram from row-major image samples, laid out according to `orientation`.
+///
+/// Returns `None` if the number of samples doesn't match the image dimensions.
+pub(crate) fn spectrogram_from_samples(
+    samples: Vec<f64>,
+    img_width: u32,
+    img_height: u32,
+    orientation: Orientation,
+) -> Option<Spectrogram> {
+    if samples.len() != img_width as usize * img_height as usize {
+        return None;
+    }
+
+    match orientation {
+        // Raw images are already stored column by column.
+        Orientation::Raw => Some(Spectrogram {
+            data: samples,
+            width: img_height,
+            height: img_width,
+            ..Default::default()
+        }),
+        Orientation::Image => {
+            let (w, h) = (img_width as usize, img_height as usize);
+            let data = (0..w)
+                .flat_map(|c| (0..h).map(move |r| r * w + c))
+                .map(|ix| samples[ix])
+                .collect();
+            Some(Spectrogram {
+                data,
+                width: img_width,
+                height: img_height,
+                ..Default::default()
+            })
+        }
+    }
+}
+
+/// Reads the (keyword, text) pairs of all uncompressed tEXt and iTXt chunks in a PNG.
+fn read_text_chunks(bytes: &[u8]) -> io::Result<Vec<(String, String)>> {
+    if bytes.len() < PNG_SIGNATURE.len() || bytes[..PNG_SIGNATURE.len()] != PNG_SIGNATURE {
+        return Err(invalid_data("not a PNG file"));
+    }
+
+    let mut text = Vec::new();
+    let mut pos = PNG_SIGNATURE.len();
+
+    // Each chunk is a length, a type, the data, and a crc
+    while pos + 8 <= bytes.len() {
+        let length = u32::from_be_bytes([bytes[pos], bytes[pos + 1], bytes[pos + 2], bytes[pos + 3]])
+            as usize;
+        let kind = &bytes[pos + 4..pos + 8];
+        let start = pos + 8;
+        let end = start + length;
+        if end + 4 > bytes.len() {
+            return Err(invalid_data("truncated PNG chunk"));
+        }
+        let data = &bytes[start..end];
+
+        match kind {
+            b"tEXt" => {
+                let mut parts = data.splitn(2, |b| *b == 0);
+                if let (Some(key), Some(value)) = (parts.next(), parts.next()) {
+                    // tEXt chunks are Latin-1 encoded
+                    let latin1 = |b: &[u8]| b.iter().map(|c| *c as char).collect::<String>();
+                    text.push((latin1(key), latin1(value)));
+                }
+            }
+            b"iTXt" => {
+                if let Some(pair) = parse_itext(data) {
+                    text.push(pair);
+                }
+            }
+            b"IEND" => break,
+            _ => {}
+        }
+
+        pos = end + 4;
+    }
+
+    Ok(text)
+}
+
+/// Parses an uncompressed iTXt chunk into a (keyword, text) pair.
+fn parse_itext(data: &[u8]) -> Option<(String, String)> {
+    let mut parts = data.splitn(2, |b| *b == 0);
+    let key = String::from_utf8(parts.next()?.to_vec()).ok()?;
+    let rest = parts.next()?;
+
+    // Compression flag and method, followed by the language tag and translated keyword
+    let (compressed, rest) = (*rest.first()?, rest.get(2..)?);
+    if compressed != 0 {
+        return None;
+    }
+    let mut parts = rest.splitn(3, |b| *b == 0);
+    let _language = parts.next()?;
+    let _translated = parts.next()?;
+    let value = String::from_utf8(parts.next()?.to_vec()).ok()?;
+
+    Some((key, value))
+}
+
+fn text_chunk(key: &str, value: &str) -> Vec<u8> {
+    let mut data: Vec<u8> = key.chars().map(|c| c as u8).collect();
+    data.push(0);
+    data.extend(value.chars().map(|c| c as u8));
+    data
+}
+
+fn itext_chunk(key: &str, value: &str) -> Vec<u8> {
+    let mut data: Vec<u8> = key.as_bytes().to_vec();
+    // Null separator, no compression, no language tag, no translated keyword
+    data.extend(&[0, 0, 0, 0, 0]);
+    data.extend(value.as_bytes());
+    data
+}
+
+fn png16_bytes(img: &GrayImage16) -> Vec<u8> {
+    // PNG stores 16-bit samples in network (big endian) order.
+    img.iter().flat_map(|v| v.to_be_bytes().to_vec()).collect()
+}
+
+fn png_error(e: png::DecodingError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
@@ -0,0 +1,49 @@
+/*!
+ * Time-domain features of each frame of audio: RMS energy, and zero-crossing rate.
+ *
+ * Frames are taken exactly as `stft::inplace::STFT` takes them (windows of `window_size` samples, every `step_size` samples, with no padding), so the features line up column for column with a spectrogram computed with the same window and step sizes:
+ *
+ * ```ignore
+ * let builder = SpectrogramBuilder::new();
+ * let spectrogram = builder.build(&samples);
+ * let energy = builder.rms(&samples);
+ * assert_eq!(energy.len(), spectrogram.width as usize);
+ * ```
+ */
+/// Magnitudes at or below this are treated as zero when counting zero crossings, as in librosa.
+const ZERO_THRESHOLD: f64 = 1e-10;
+
+/// Iterates over the frames of some samples, in the same way as `stft::inplace::STFT`.
+///
+/// # Panics
+/// panics if `window_size` or `step_size` is zero
+pub fn frames(
+    samples: &[f64],
+    window_size: usize,
+    step_size: usize,
+) -> impl Iterator<Item = &[f64]> {
+    samples.windows(window_size).step_by(step_size)
+}
+
+/// The root mean square energy of each frame, as `librosa.feature.rms`.
+pub fn rms(samples: &[f64], window_size: usize, step_size: usize) -> Vec<f64> {
+    frames(samples, window_size, step_size)
+        .map(|frame| (frame.iter().map(|s| s * s).sum::<f64>() / frame.len() as f64).sqrt())
+        .collect()
+}
+
+/// The zero-crossing rate of each frame: the number of times consecutive samples change sign, divided by the length of the frame, as `librosa.feature.zero_crossing_rate`.
+///
+/// As in librosa, samples very close to zero count as zero, and zero counts as positive.
+pub fn zero_crossing_rate(samples: &[f64], window_size: usize, step_size: usize) -> Vec<f64> {
+    let negative = |s: &f64| *s < -ZERO_THRESHOLD;
+    frames(samples, window_size, step_size)
+        .map(|frame| {
+            let crossings = frame
+                .windows(2)
+                .filter(|pair| negative(&pair[0]) != negative(&pair[1]))
+                .count();
+            crossings as f64 / frame.len() as f64
+        })
+        .collect()
+}
@@ -0,0 +1,192 @@
+/*!
+ * Harmonic-percussive source separation, equivalent to `librosa.decompose.hpss`.
+ *
+ * Harmonic sounds (sustained notes) show up in a spectrogram as horizontal lines, and percussive sounds (e.g. ride cymbals and hi-hats) as vertical lines. Median filtering each row of the magnitudes across time gives an estimate of the harmonic component, and median filtering each column across frequency gives an estimate of the percussive component. Comparing the two gives a mask for each component, which is applied to the original magnitudes.
+ *
+ * `Spectrogram::hpss` separates an existing spectrogram of linear magnitudes (computed with `Scaling::Magnitude` and `Normalisation::None`), while `SpectrogramBuilder::build_hpss` separates the magnitudes before scaling and normalising them as usual, and `SpectrogramBuilder::separate` masks the complex STFT and inverts it to give separated audio:
+ *
+ * ```ignore
+ * let (harmonic, percussive) = Spectrogram::builder().separate(&Hpss::default(), &samples);
+ * ```
+ */
+use super::Spectrogram;
+
+use rayon::prelude::*;
+
+/// How the filtered magnitudes are turned into masks.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Mask {
+    /// Wiener-like soft masks, with the filtered magnitudes raised to `power` (as librosa's `power` argument).
+    Soft { power: f64 },
+    /// Binary masks: each cell belongs entirely to whichever component is larger (as librosa's `power=np.inf`).
+    Hard,
+}
+
+/// The parameters of harmonic-percussive source separation. The default parameters are those of `librosa.decompose.hpss`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Hpss {
+    /// The length (in frames) of the median filter across time, for the harmonic component.
+    pub harmonic_kernel: usize,
+    /// The length (in bins) of the median filter across frequency, for the percussive component.
+    pub percussive_kernel: usize,
+    pub mask: Mask,
+    /// How much larger the harmonic estimate must be than the percussive estimate for a cell to count as harmonic. Margins above 1 leave a residual component that is in neither.
+    pub harmonic_margin: f64,
+    /// How much larger the percussive estimate must be than the harmonic estimate for a cell to count as percussive.
+    pub percussive_margin: f64,
+}
+
+impl Default for Hpss {
+    fn default() -> Self {
+        Hpss {
+            harmonic_kernel: 31,
+            percussive_kernel: 31,
+            mask: Mask::Soft { power: 2.0 },
+            harmonic_margin: 1.0,
+            percussive_margin: 1.0,
+        }
+    }
+}
+
+impl Hpss {
+    /// Computes the harmonic and percussive masks of some (column major) linear magnitudes.
+    ///
+    /// # Panics
+    /// panics if either kernel is zero, or either margin is less than 1
+    pub fn masks(&self, magnitudes: &[f64], height: usize) -> (Vec<f64>, Vec<f64>) {
+        assert!(self.harmonic_kernel > 0 && self.percussive_kernel > 0);
+        assert!(self.harmonic_margin >= 1.0 && self.percussive_margin >= 1.0);
+
+        let harmonic = median_filter_rows(magnitudes, height, self.harmonic_kernel);
+        let percussive = median_filter_columns(magnitudes, height, self.percussive_kernel);
+
+        // With no margins, cells where both estimates are zero are split evenly
+        let split_zeros = self.harmonic_margin == 1.0 && self.percussive_margin == 1.0;
+        let mask = |x: f64, reference: f64| match self.mask {
+            Mask::Soft { power } => softmask(x, reference, power, split_zeros),
+            Mask::Hard => {
+                if x > reference {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        };
+
+        harmonic
+            .iter()
+            .zip(percussive.iter())
+            .map(|(h, p)| {
+                (
+                    mask(*h, p * self.harmonic_margin),
+                    mask(*p, h * self.percussive_margin),
+                )
+            })
+            .unzip()
+    }
+}
+
+impl Spectrogram {
+    /// Separates a spectrogram of linear magnitudes into its harmonic and percussive components.
+    ///
+    /// The `component` of each spectrogram's parameters is set to "harmonic" or "percussive".
+    pub fn hpss(&self, hpss: &Hpss) -> (Spectrogram, Spectrogram) {
+        let (harmonic, percussive) = hpss.masks(&self.data[..], self.height as usize);
+        (
+            self.masked(&harmonic[..], "harmonic"),
+            self.masked(&percussive[..], "percussive"),
+        )
+    }
+
+    fn masked(&self, mask: &[f64], component: &str) -> Spectrogram {
+        let mut parameters = self.parameters_or_default();
+        parameters.component = component.to_string();
+
+        Spectrogram {
+            width: self.width,
+            height: self.height,
+            data: self
+                .data
+                .iter()
+                .zip(mask.iter())
+                .map(|(v, m)| v * m)
+                .collect(),
+            parameters: Some(parameters),
+            ..self.without_data()
+        }
+    }
+}
+
+/// `librosa.util.softmask`: the share of `x` in `x + reference`, after raising both to `power`.
+fn softmask(x: f64, reference: f64, power: f64, split_zeros: bool) -> f64 {
+    let z = x.max(reference);
+    if z < f64::MIN_POSITIVE {
+        return if split_zeros { 0.5 } else { 0.0 };
+    }
+    let x = (x / z).powf(power);
+    let reference = (reference / z).powf(power);
+    x / (x + reference)
+}
+
+/// Median filters each row (bin) across time.
+fn median_filter_rows(data: &[f64], height: usize, kernel: usize) -> Vec<f64> {
+    if height == 0 {
+        return Vec::new();
+    }
+    let width = data.len() / height;
+    let rows: Vec<Vec<f64>> = (0..height)
+        .into_par_iter()
+        .map(|bin| {
+            let row: Vec<f64> = (0..width).map(|t| data[t * height + bin]).collect();
+            median_filter(&row[..], kernel)
+        })
+        .collect();
+
+    let mut filtered = vec![0.0; data.len()];
+    for (bin, row) in rows.iter().enumerate() {
+        for (t, value) in row.iter().enumerate() {
+            filtered[t * height + bin] = *value;
+        }
+    }
+    filtered
+}
+
+/// Median filters each column (frame) across frequency.
+fn median_filter_columns(data: &[f64], height: usize, kernel: usize) -> Vec<f64> {
+    if height == 0 {
+        return Vec::new();
+    }
+    data.par_chunks(height)
+        .flat_map(|column| median_filter(column, kernel))
+        .collect()
+}
+
+/// A median filter with reflected edges, as `scipy.ndimage.median_filter` (which takes the upper median for even kernels).
+fn median_filter(values: &[f64], kernel: usize) -> Vec<f64> {
+    let n = values.len() as isize;
+    let mut window = Vec::with_capacity(kernel);
+    (0..n)
+        .map(|i| {
+            window.clear();
+            let start = i - kernel as isize / 2;
+            window.extend((start..start + kernel as isize).map(|j| values[reflect(j, n)]));
+            window.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+            window[kernel / 2]
+        })
+        .collect()
+}
+
+/// Reflects an index about the edges of `0..n`, as in scipy's "reflect" mode (`d c b a | a b c d | d c b a`).
+fn reflect(mut i: isize, n: isize) -> usize {
+    loop {
+        if i < 0 {
+            i = -i - 1;
+        } else if i >= n {
+            i = 2 * n - i - 1;
+        } else {
+            return i as usize;
+        }
+    }
+}
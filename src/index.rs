@@ -0,0 +1,194 @@
+/*!
+ * Indexing spectrograms by column, row, time and frequency.
+ *
+ * Spectrogram data is stored column by column, so the value of frequency bin `bin` in frame `frame` is `data[frame * height + bin]`. The methods in this module hide that arithmetic, and convert between frames and seconds, and between bins and Hz, using the spectrogram's `parameters` (or the parameters used by `from_buffer`, if the spectrogram has none).
+ *
+ * ```ignore
+ * // The spectrum ten seconds into the track
+ * let column = spectrogram.column(spectrogram.seconds_to_frame(10.0));
+ * // Just the bass, for the first thirty seconds
+ * let bass = spectrogram.slice_seconds(0.0, 30.0).slice_hz(0.0, 250.0);
+ * ```
+ */
+use super::mel::Mel;
+use super::{Parameters, Spectrogram, SAMPLE_RATE, STEP_SIZE, WINDOW_SIZE};
+
+use std::ops::Range;
+use std::slice::Chunks;
+
+impl Spectrogram {
+    /// The spectrogram's parameters, or those used by `from_buffer` if it has none.
+    pub fn parameters_or_default(&self) -> Parameters {
+        self.parameters.clone().unwrap_or_else(|| Parameters {
+            sample_rate: SAMPLE_RATE,
+            window_size: WINDOW_SIZE as u32,
+            step_size: STEP_SIZE as u32,
+            fft_size: WINDOW_SIZE as u32,
+            ..Default::default()
+        })
+    }
+
+    /// The values of every frequency bin in a single frame.
+    ///
+    /// # Panics
+    /// panics if `frame` is out of range
+    pub fn column(&self, frame: usize) -> &[f64] {
+        let height = self.height as usize;
+        &self.data[frame * height..(frame + 1) * height]
+    }
+
+    /// Iterates over the columns (frames) of the spectrogram, in order.
+    pub fn columns(&self) -> Chunks<'_, f64> {
+        self.data.chunks(self.height.max(1) as usize)
+    }
+
+    /// Iterates over the values of a single frequency bin, across every frame.
+    ///
+    /// # Panics
+    /// panics if `bin` is out of range
+    pub fn row(&self, bin: usize) -> impl Iterator<Item = f64> + '_ {
+        assert!(bin < self.height as usize);
+        self.data[bin..]
+            .iter()
+            .step_by(self.height as usize)
+            .cloned()
+    }
+
+    /// The value of frequency bin `bin` in frame `frame`, or `None` if either is out of range.
+    pub fn get(&self, frame: usize, bin: usize) -> Option<f64> {
+        if frame >= self.width as usize || bin >= self.height as usize {
+            return None;
+        }
+        self.data.get(frame * self.height as usize + bin).cloned()
+    }
+
+    /// The time (in seconds) of the start of a frame, as in `librosa.frames_to_time`.
+    pub fn frame_to_seconds(&self, frame: usize) -> f64 {
+        let parameters = self.parameters_or_default();
+        frame as f64 * parameters.step_size as f64 / parameters.sample_rate as f64
+    }
+
+    /// The frame containing a time (in seconds), as in `librosa.time_to_frames`. Negative times map to the first frame.
+    ///
+    /// Note that this may be beyond the last frame of the spectrogram.
+    pub fn seconds_to_frame(&self, seconds: f64) -> usize {
+        let parameters = self.parameters_or_default();
+        (seconds * parameters.sample_rate as f64 / parameters.step_size as f64)
+            .floor()
+            .max(0.0) as usize
+    }
+
+    /// The frequency (in Hz) of a frequency bin, as in `librosa.fft_frequencies` (or `librosa.cqt_frequencies`, for constant-Q spectrograms, and `librosa.mel_frequencies`, for mel spectrograms).
+    pub fn bin_to_hz(&self, bin: usize) -> f64 {
+        let parameters = self.parameters_or_default();
+        if let Some(mel) = Mel::of_rows(&parameters, self.height) {
+            return mel.frequency(bin as f64 + 1.0, parameters.sample_rate);
+        }
+        if parameters.bins_per_octave > 0 {
+            // Constant-Q bins are log-spaced
+            return parameters.fmin * 2f64.powf(bin as f64 / parameters.bins_per_octave as f64);
+        }
+        parameters.fmin + bin as f64 * Self::bin_width(&parameters)
+    }
+
+    /// The frequency bin nearest to a frequency (in Hz). Frequencies below `fmin` map to the first bin.
+    ///
+    /// Note that this may be beyond the last bin of the spectrogram.
+    pub fn hz_to_bin(&self, hz: f64) -> usize {
+        let parameters = self.parameters_or_default();
+        let bin = if let Some(mel) = Mel::of_rows(&parameters, self.height) {
+            mel.position(hz, parameters.sample_rate) - 1.0
+        } else if parameters.bins_per_octave > 0 {
+            parameters.bins_per_octave as f64 * (hz / parameters.fmin).log2()
+        } else {
+            (hz - parameters.fmin) / Self::bin_width(&parameters)
+        };
+        // NaN (for non-positive frequencies of constant-Q spectrograms) also maps to the first bin
+        bin.round().max(0.0) as usize
+    }
+
+    /// A new spectrogram containing only the given range of frames.
+    ///
+    /// Frame indices (and so `frame_to_seconds`) of the new spectrogram are relative to the start of the range.
+    ///
+    /// # Panics
+    /// panics if the range is out of bounds
+    pub fn slice_frames(&self, frames: Range<usize>) -> Self {
+        let height = self.height as usize;
+        Spectrogram {
+            width: frames.len() as u32,
+            height: self.height,
+            data: self.data[frames.start * height..frames.end * height].to_vec(),
+            ..self.without_data()
+        }
+    }
+
+    /// A new spectrogram containing only the frames between `start` and `end` (in seconds), clamped to the length of the spectrogram.
+    pub fn slice_seconds(&self, start: f64, end: f64) -> Self {
+        let width = self.width as usize;
+        let start = self.seconds_to_frame(start).min(width);
+        let end = self.seconds_to_frame(end).max(start).min(width);
+        self.slice_frames(start..end)
+    }
+
+    /// A new spectrogram containing only the given range of frequency bins.
+    ///
+    /// The `fmin` and `fmax` of the new spectrogram's parameters are updated to match, so `bin_to_hz` still gives the correct frequencies.
+    ///
+    /// # Panics
+    /// panics if the range is out of bounds
+    pub fn slice_bins(&self, bins: Range<usize>) -> Self {
+        assert!(bins.start <= bins.end && bins.end <= self.height as usize);
+
+        let data = if bins.is_empty() {
+            Vec::new()
+        } else {
+            self.columns()
+                .flat_map(|column| column[bins.clone()].iter().cloned())
+                .collect()
+        };
+
+        let mut sliced = Spectrogram {
+            width: if bins.is_empty() { 0 } else { self.width },
+            height: bins.len() as u32,
+            data,
+            ..self.without_data()
+        };
+        let parameters = self.parameters_or_default();
+        let fmin = match Mel::of_rows(&parameters, self.height) {
+            // The lowest edge of a mel filterbank is that of its first band, below the band's centre
+            Some(mel) => mel.frequency(bins.start as f64, parameters.sample_rate),
+            None => self.bin_to_hz(bins.start),
+        };
+        let fmax = self.bin_to_hz(bins.end);
+        if let Some(ref mut parameters) = sliced.parameters {
+            parameters.fmin = fmin;
+            parameters.fmax = fmax;
+        }
+        sliced
+    }
+
+    /// A new spectrogram containing only the bins between `low` and `high` (in Hz), clamped to the height of the spectrogram.
+    pub fn slice_hz(&self, low: f64, high: f64) -> Self {
+        let height = self.height as usize;
+        let start = self.hz_to_bin(low).min(height);
+        let end = self.hz_to_bin(high).max(start).min(height);
+        self.slice_bins(start..end)
+    }
+
+    /// The frequency spacing (in Hz) between adjacent bins.
+    fn bin_width(parameters: &Parameters) -> f64 {
+        parameters.sample_rate as f64 / parameters.fft_size as f64
+    }
+
+    /// A copy of everything but the spectrogram's data.
+    pub(crate) fn without_data(&self) -> Self {
+        Spectrogram {
+            schema_version: self.schema_version,
+            parameters: self.parameters.clone(),
+            source: self.source.clone(),
+            created: self.created,
+            ..Default::default()
+        }
+    }
+}
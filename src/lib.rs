@@ -15,42 +15,133 @@
 //! spectrogram = librosa.amplitude_to_db(M, ref=np.max)[0:1024, :]
 //! ```
 //!
+//! The STFT, dB conversion and normalisation parameters can be changed by computing spectrograms with a `SpectrogramBuilder` (see `Spectrogram::builder()`). In particular, dB values can be computed relative to a fixed reference, rather than the peak of each track, so that loudness can be compared across tracks. A constant-Q transform (see the `cqt` module) can be computed instead of an STFT, magnitudes can be scaled with per-channel energy normalisation (see the `pcen` module) instead of being converted to dB, and values can be normalised to a fixed dB range, rather than the range of each track (see `normalisation::Normalisation`). The conversions themselves are available in the `db` module.
+//!
+//! # Analysis
+//!
+//! Alongside the spectrogram itself, tizol can compute:
+//!
+//! - chroma features, and the key of a track (the `chroma` module)
+//! - mel spectrograms, MFCCs and their deltas (the `mel` and `mfcc` modules)
+//! - descriptors of the shape of the spectrum of each frame (the `spectral` module)
+//! - the RMS energy and zero-crossing rate of each frame, aligned with the columns of the spectrogram (the `frame` module)
+//! - the harmonic and percussive components of a track, as spectrograms or as audio (the `hpss` module)
+//! - onset strength envelopes, and autocorrelation and Fourier tempograms, which can be rendered like any other spectrogram (the `onset` and `tempogram` modules)
+//!
+//! Given a beat grid, it can also:
+//!
+//! - estimate the swing ratio of a track, and how it changes from section to section (the `swing` module)
+//! - group beats into bars, phrases and choruses (the `meter` module)
+//! - find the structure of a tune (intro, heads, solos, shout chorus and outro) from a self-similarity matrix (the `segment` module)
+//! - aggregate any frame-aligned features between beats, into a tempo-invariant spectrogram (the `sync` module)
+//!
+//! # Indexing spectrograms
+//!
+//! Spectrogram data is stored column by column in a flat `data` vector. Methods such as `Spectrogram::column`, `Spectrogram::row` and `Spectrogram::get` index it without repeating the arithmetic, while `frame_to_seconds`/`seconds_to_frame` and `bin_to_hz`/`hz_to_bin` convert between indices and physical units. See the `index` module for details.
+//!
 //! # Visualising spectrograms
 //!
 //! Tizol provides the `Spect::as_image()` method for visualising already-computed spectrograms. This method is unfortunately quite slow as in order to maintain parity with the output of librosa, it uses the `Magma` colourmap from the scarlet crate to compute pixel colours. For some reason, this computation is very slow, and even with parallelisation it is still roughly 10x slower than computing the actual spectrogram.
 //!
 //! # Protobuf support
 //!
-//! Spectrogram's support protobuffers through the prost crate, meaning that spectrograms implement the `Message` trait.
+//! Spectrogram's support protobuffers through the prost crate, meaning that spectrograms implement the `Message` trait. Alongside the data, each message records the parameters the spectrogram was computed with, a description of its source audio, a creation timestamp, and the version of the schema it was written with (see `src/spectrogram.proto`).
+//!
+//! As storing each element of a spectrogram as a double is quite expensive, the data can also be stored in single precision, or quantised to 8 or 16 bits, via `Spectrogram::with_encoding`. Use `Spectrogram::decode_expanded` to read spectrograms written with any encoding.
+//!
+//! Large collections of spectrograms can be stored together in a single indexed file using the `archive` module, and very long spectrograms can be streamed a few columns at a time using the `chunked` module.
+//!
+//! # NumPy support
+//!
+//! Spectrograms can be written to (and read from) NumPy's `.npy` and `.npz` formats, with the same `(frequency, time)` layout as `librosa.stft`. See the `numpy` module for details.
+//!
+//! # ndarray support
+//!
+//! With the `ndarray` feature enabled, `Spectrogram::view()` and `Spectrogram::view_mut()` expose the spectrogram data as a `(frequency, time)` shaped `ndarray` array, without copying it, and `Spectrogram::from_array` creates a spectrogram from an array. See the `array` module for details.
+//!
+//! # Serde support
+//!
+//! With the `serde` feature enabled, `Spectrogram` (along with the other protobuf messages), `WindowType` and the other configuration types implement serde's `Serialize` and `Deserialize` traits, so they can be written in any format supported by serde (e.g. JSON, CBOR, MessagePack or bincode).
 //!
 //! # Naming
 //!
 //! Tizol is part of the "Ellington" project - a set of tools designed to make it easier for swing dance DJ's to automatically calculate the tempo of swing music. Each component of the project is named after a member of (or arranger for) Duke Ellington's band. Tizol is named after [Juan Tizol](https://en.wikipedia.org/wiki/Juan_Tizol), a solid rock of the trombone section, and the composer of "Caravan", one of the most famous jazz standards.
 
 // extern crate stft;
+#[cfg(feature = "ndarray")]
+pub mod array;
+pub mod archive;
+pub mod builder;
+pub mod chroma;
+pub mod chunked;
+pub mod cqt;
+pub mod db;
+pub mod encoding;
+pub mod export;
+pub mod frame;
+pub mod hpss;
+pub mod index;
+pub mod mel;
+pub mod meter;
+pub mod mfcc;
+pub mod normalisation;
+pub mod numpy;
+pub mod onset;
+pub mod pcen;
+pub mod segment;
+pub mod spectral;
 pub mod stft;
+pub mod swing;
+pub mod sync;
+pub mod tempogram;
 use stft::streaming::STFT as StreamingSTFT;
-use stft::inplace::STFT as InplaceSTFT;
-use stft::WindowType;
+use builder::SpectrogramBuilder;
+use export::Orientation;
 
 extern crate image;
-use image::{GrayImage, ImageBuffer, RgbImage};
+use image::{GrayImage, ImageBuffer, Luma, RgbImage};
 
 extern crate hodges;
-use hodges::*;
 
 extern crate scarlet;
 use crate::scarlet::colormap::ColorMap;
 
-use rayon::prelude::*;
-
 use std::path::PathBuf;
 
 extern crate prost;
 
+extern crate sha2;
+use sha2::{Digest, Sha256};
+
 // Include spectrogram structure from protobuf definition (built in build.rs)
 include!(concat!(env!("OUT_DIR"), "/tizol.rs"));
 
+/// The sample rate (in Hz) that `from_file` decodes audio at, and that `from_buffer` expects its samples to be at.
+pub const SAMPLE_RATE: u32 = 44100;
+
+/// The STFT window size used by `from_buffer`
+pub const WINDOW_SIZE: usize = 2048;
+
+/// The STFT step (hop) size used by `from_buffer`
+pub const STEP_SIZE: usize = WINDOW_SIZE / 4;
+
+/// The dynamic range (in dB) below the peak that `from_buffer` clips the spectrogram to
+pub const TOP_DB: f64 = 80.0;
+
+/// The minimum amplitude considered by `from_buffer` when converting to dB
+pub const AMIN: f64 = 1e-5;
+
+/// The version of the protobuf schema written by this version of tizol. See `Spectrogram::schema_version`.
+///
+/// Only bumped when the meaning of an existing field changes; fields added since (such as `encoding`) decode with their defaults from older messages.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A 16-bit grayscale image, as produced by `Spectrogram::as_image_bw16`
+pub type GrayImage16 = ImageBuffer<Luma<u16>, Vec<u16>>;
+
+/// A single precision floating point grayscale image, as produced by `Spectrogram::as_image_f32_raw`
+pub type GrayImageF32 = ImageBuffer<Luma<f32>, Vec<f32>>;
+
 impl Spectrogram {
     /// Creates a spectrogram object from a filepath.
     ///
@@ -58,113 +149,19 @@ impl Spectrogram {
     ///
     /// Returns `None` if `State::from_file()` fails for any reason.
     pub fn from_file<P: Into<PathBuf>>(filename: P) -> Option<Self> {
-        // Create a hodges state object to load the audio data
-        let state: State<f32> = State::from_file(filename)?;
-
-        // Collect the audio samples into a single buffer for processing.
-        let audio_samples: Vec<f64> = state.map(|f| f as f64).collect();
-
-        Some(Self::from_buffer(&audio_samples))
+        SpectrogramBuilder::new().build_from_file(filename)
     }
 
     /// Creates a spectrogram object from a vector of PCM encoded floating point samples.
     ///
     /// In FFMPEG terms, these are single channel f32le samples, at a sample rate of 44100hz
-    pub fn from_buffer(audio_samples: &Vec<f64>) -> Self {
-        let window_size = 2048;
-        let step_size = window_size / 4;
-        // Initialise the stft machinery.
-        let stft = InplaceSTFT::<f64>::new(
-            WindowType::Hanning, /*The STFT window type*/
-            window_size,         /* Window size */
-            step_size,           /* Step size */
-        );
-
-        // Adjustable - for now, use the whole buffer.
-        let low_freq = 0;
-        let hi_freq = stft.output_size();
-
-        // Perform the STFT across the samples
-        let mut spectrogram_output = stft.par_iter_stft(audio_samples); 
-
-        // Compute the amplitude_to_db of the result.
-        Self::amplitude_to_db(&mut spectrogram_output[..]);
-
-        // Normalize the output.
-        Self::normalize_buffer_inplace(&mut spectrogram_output[..]);
-
-        // Finally, calculate the height/width of the data.
-        let height = hi_freq - low_freq;
-        let width = spectrogram_output.len() / height; // Guaranteed to be divisible
-
-        Spectrogram {
-            data: spectrogram_output,
-            width: width as u32,
-            height: height as u32,
-        }
-    }
-
-    fn normalize_buffer_inplace(buffer: &mut [f64]) -> () {
-        let (min, max): (f64, f64) =
-            buffer
-                .iter()
-                .map(|v| v.abs())
-                .fold((std::f64::MAX, std::f64::MIN), |(mi, ma), x| {
-                    let new_mi = if x < mi { x } else { mi };
-                    let new_ma = if x > ma { x } else { ma };
-                    (new_mi, new_ma)
-                });
-
-        let range = max - min;
-        buffer
-            .iter_mut()
-            .for_each(|v| *v = 1.0 - ((v.abs() - min) / range));
-    }
-
-    fn amplitude_to_db(s: &mut [f64]) -> () {
-        let amin: f64 = 1e-5;
-        let top_db: f64 = 80.0;
-
-        let ref_value: f64 =
-            s.iter()
-                .map(|x| x.abs())
-                .fold(std::f64::MIN, |m, x| if x > m { x } else { m });
-
-        // Don't forget to elementwise square S first!
-        s.iter_mut().for_each(|v| {
-            // Calculate magnitude
-            // *v = v.abs(); // we can skip this because it all washes out in the power
-            // Calculate power
-            *v = *v * *v;
-        });
-        Self::power_to_db(s, ref_value.powf(2.0), amin.powf(2.0), top_db);
-    }
-
-    fn power_to_db(s: &mut [f64], ref_value: f64, amin: f64, top_db: f64) -> () {
-        if amin <= 0.0 {
-            panic!("amin must be >= 0");
-        }
-        if top_db < 0.0 {
-            panic!("top_db must be > 0");
-        }
-
-        let ref_value = ref_value.abs();
-
-        // Pull this value out of the inner loop
-        let tammrf = 10.0 * amin.max(ref_value).log10();
-
-        s.par_iter_mut().for_each(|v| {
-            // Calculate log_spec
-            *v = (10.0 * amin.max(*v).log10()) - tammrf;
-            // *v -= tammrf;
-        });
-
-        let max: f64 = s
-            .iter()
-            .fold(std::f64::MIN, |m, x| if *x > m { *x } else { m });
+    pub fn from_buffer(audio_samples: &[f64]) -> Self {
+        SpectrogramBuilder::new().build(audio_samples)
+    }
 
-        // Second loop after we have the max
-        s.iter_mut().for_each(|v| *v = v.max(max - top_db));
+    /// A builder for computing spectrograms with parameters other than those used by `from_buffer`.
+    pub fn builder() -> SpectrogramBuilder {
+        SpectrogramBuilder::new()
     }
 
     /// Generates an image::Result from a spectrogram.
@@ -202,8 +199,7 @@ impl Spectrogram {
             .enumerate()
             .for_each(|(c, column)| {
                 column.iter().enumerate().for_each(|(r, colour)| {
-                    let colour = ((*colour) * 256.0) as u8;
-                    let p = image::Luma([colour]);
+                    let p = image::Luma([Self::quantise_u8(*colour)]);
                     img.put_pixel(c as u32, r as u32, p);
                 })
             });
@@ -212,8 +208,95 @@ impl Spectrogram {
     }
 
     pub fn as_image_bw_raw(&self) -> GrayImage {
-        let u8dat: Vec<u8> = self.data[..].iter().map(|c| ((*c) * 256.0) as u8).collect();
+        let u8dat: Vec<u8> = self.data[..].iter().map(|c| Self::quantise_u8(*c)).collect();
 
         ImageBuffer::from_vec(self.height as u32, self.width as u32, u8dat).unwrap()
     }
+
+    /// Generates a 16-bit grayscale image from a spectrogram, with the same orientation as `as_image_bw`.
+    ///
+    /// Images with this pixel type can't be saved through `image`, so use `export::save_png16` to write them.
+    pub fn as_image_bw16(&self) -> GrayImage16 {
+        let mut img: GrayImage16 = ImageBuffer::new(self.width as u32, self.height as u32);
+
+        self.data[..]
+            .chunks(self.height as usize)
+            .enumerate()
+            .for_each(|(c, column)| {
+                column.iter().enumerate().for_each(|(r, colour)| {
+                    let p = image::Luma([Self::quantise_u16(*colour)]);
+                    img.put_pixel(c as u32, r as u32, p);
+                })
+            });
+
+        img
+    }
+
+    /// Generates a 16-bit grayscale image from a spectrogram, with the same orientation as `as_image_bw_raw`.
+    pub fn as_image_bw16_raw(&self) -> GrayImage16 {
+        let u16dat: Vec<u16> = self.data[..].iter().map(|c| Self::quantise_u16(*c)).collect();
+
+        ImageBuffer::from_vec(self.height as u32, self.width as u32, u16dat).unwrap()
+    }
+
+    /// Generates a single precision floating point image from a spectrogram, with the same orientation as `as_image_bw_raw`.
+    ///
+    /// Values are not quantised or clamped, so (aside from the conversion to `f32`) this is lossless. Use `export::save_tiff_f32` to write it.
+    pub fn as_image_f32_raw(&self) -> GrayImageF32 {
+        let f32dat: Vec<f32> = self.data[..].iter().map(|c| *c as f32).collect();
+
+        ImageBuffer::from_vec(self.height as u32, self.width as u32, f32dat).unwrap()
+    }
+
+    /// Recovers a spectrogram from an 8-bit grayscale image produced by `as_image_bw` or `as_image_bw_raw`, as described by `orientation`.
+    ///
+    /// Values are recovered to within the 8-bit quantisation step. Images saved by earlier versions of tizol were quantised differently, and should be loaded with `from_legacy_image` instead.
+    pub fn from_image(img: &GrayImage, orientation: Orientation) -> Self {
+        let samples = img.iter().map(|p| Self::dequantise_u8(*p)).collect();
+        export::spectrogram_from_samples(samples, img.width(), img.height(), orientation).unwrap()
+    }
+
+    /// Recovers a spectrogram from a 16-bit grayscale image produced by `as_image_bw16` or `as_image_bw16_raw`, as described by `orientation`.
+    pub fn from_image16(img: &GrayImage16, orientation: Orientation) -> Self {
+        let samples = img.iter().map(|p| Self::dequantise_u16(*p)).collect();
+        export::spectrogram_from_samples(samples, img.width(), img.height(), orientation).unwrap()
+    }
+
+    /// Recovers a spectrogram from an 8-bit grayscale image produced by an earlier version of tizol.
+    ///
+    /// Earlier versions quantised values by truncating `value * 256`, so each pixel is mapped back to the centre of the interval it was truncated from. Note that these versions also wrapped values of exactly 1.0 (i.e. the loudest parts of the spectrogram) around to 0, which can't be undone.
+    pub fn from_legacy_image(img: &GrayImage, orientation: Orientation) -> Self {
+        let samples = img.iter().map(|p| Self::dequantise_legacy_u8(*p)).collect();
+        export::spectrogram_from_samples(samples, img.width(), img.height(), orientation).unwrap()
+    }
+
+    /// Inverse of the `(value * 256.0) as u8` quantisation used by earlier versions of tizol
+    fn dequantise_legacy_u8(v: u8) -> f64 {
+        (v as f64 + 0.5) / 256.0
+    }
+
+    /// Inverse of `quantise_u8`
+    fn dequantise_u8(v: u8) -> f64 {
+        v as f64 / u8::MAX as f64
+    }
+
+    /// Inverse of `quantise_u16`
+    fn dequantise_u16(v: u16) -> f64 {
+        v as f64 / u16::MAX as f64
+    }
+
+    /// Quantises a value in [0,1] to the full `u8` range, saturating values outside of it.
+    fn quantise_u8(v: f64) -> u8 {
+        (v.clamp(0.0, 1.0) * u8::MAX as f64).round() as u8
+    }
+
+    /// Quantises a value in [0,1] to the full `u16` range, saturating values outside of it.
+    fn quantise_u16(v: f64) -> u16 {
+        (v.clamp(0.0, 1.0) * u16::MAX as f64).round() as u16
+    }
+}
+
+/// Computes a hex encoded SHA-256 hash of some content (e.g. an audio file), for identifying the source of a spectrogram.
+pub fn content_hash(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
 }
@@ -0,0 +1,174 @@
+/*!
+ * Mel filterbanks and mel spectrograms, equivalent to `librosa.filters.mel` and `librosa.feature.melspectrogram`.
+ *
+ * A mel spectrogram sums the power of the bins of an STFT spectrogram through a bank of overlapping triangular filters, evenly spaced on the mel scale, which approximates the frequency resolution of human hearing.
+ *
+ * Mel spectrograms are computed from the squared values of a spectrogram, so for results comparable with librosa, compute the spectrogram with `Scaling::Magnitude` and `Normalisation::None`. The rows of a mel spectrogram are mel bands: the mel scale is recorded in its parameters, so `bin_to_hz` gives the centre frequency of each band.
+ */
+use super::{Parameters, Spectrogram};
+
+/// The parameters of a mel filterbank. The default parameters are those of `librosa.filters.mel`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mel {
+    /// The number of mel bands.
+    pub n_mels: usize,
+    /// The lowest frequency (in Hz) covered by the filterbank.
+    pub fmin: f64,
+    /// The highest frequency (in Hz) covered by the filterbank, or `None` for the Nyquist frequency.
+    pub fmax: Option<f64>,
+    /// Use the HTK mel scale, rather than the Slaney mel scale.
+    pub htk: bool,
+}
+
+impl Default for Mel {
+    fn default() -> Self {
+        Mel {
+            n_mels: 128,
+            fmin: 0.0,
+            fmax: None,
+            htk: false,
+        }
+    }
+}
+
+/// The boundary (in Hz, and in mels) between the linear and logarithmic parts of the Slaney mel scale.
+const MIN_LOG_HZ: f64 = 1000.0;
+const MIN_LOG_MEL: f64 = MIN_LOG_HZ / F_SP;
+/// The width (in Hz) of a mel in the linear part of the Slaney mel scale.
+const F_SP: f64 = 200.0 / 3.0;
+
+/// Converts a frequency (in Hz) to mels, as `librosa.hz_to_mel`.
+pub fn hz_to_mel(hz: f64, htk: bool) -> f64 {
+    if htk {
+        return 2595.0 * (1.0 + hz / 700.0).log10();
+    }
+    if hz >= MIN_LOG_HZ {
+        MIN_LOG_MEL + (hz / MIN_LOG_HZ).ln() / log_step()
+    } else {
+        hz / F_SP
+    }
+}
+
+/// Converts mels to a frequency (in Hz), as `librosa.mel_to_hz`.
+pub fn mel_to_hz(mel: f64, htk: bool) -> f64 {
+    if htk {
+        return 700.0 * (10f64.powf(mel / 2595.0) - 1.0);
+    }
+    if mel >= MIN_LOG_MEL {
+        MIN_LOG_HZ * (log_step() * (mel - MIN_LOG_MEL)).exp()
+    } else {
+        F_SP * mel
+    }
+}
+
+/// The step size of the logarithmic part of the Slaney mel scale.
+fn log_step() -> f64 {
+    6.4f64.ln() / 27.0
+}
+
+impl Mel {
+    /// The `n_mels + 2` frequencies (in Hz) of the edges and centres of the filters, evenly spaced in mels between `fmin` and `fmax`.
+    pub fn frequencies(&self, sample_rate: u32) -> Vec<f64> {
+        (0..self.n_mels + 2)
+            .map(|i| self.frequency(i as f64, sample_rate))
+            .collect()
+    }
+
+    /// The mel filterbank whose bands are the rows of a spectrogram with the given parameters and height, or `None` if it isn't a mel spectrogram.
+    pub(crate) fn of_rows(parameters: &Parameters, height: u32) -> Option<Self> {
+        let htk = match &parameters.mel_scale[..] {
+            "slaney" => false,
+            "htk" => true,
+            _ => return None,
+        };
+        Some(Mel {
+            n_mels: height as usize,
+            fmin: parameters.fmin,
+            fmax: Some(parameters.fmax),
+            htk,
+        })
+    }
+
+    /// The frequency (in Hz) at a (fractional) position in `frequencies`, so that the centre of band `k` is at `k + 1`.
+    pub(crate) fn frequency(&self, position: f64, sample_rate: u32) -> f64 {
+        let fmax = self.fmax.unwrap_or(sample_rate as f64 / 2.0);
+        let (low, high) = (hz_to_mel(self.fmin, self.htk), hz_to_mel(fmax, self.htk));
+        mel_to_hz(
+            low + (high - low) * position / (self.n_mels + 1) as f64,
+            self.htk,
+        )
+    }
+
+    /// The (fractional) position of a frequency (in Hz) in `frequencies`: the inverse of `frequency`.
+    pub(crate) fn position(&self, hz: f64, sample_rate: u32) -> f64 {
+        let fmax = self.fmax.unwrap_or(sample_rate as f64 / 2.0);
+        let (low, high) = (hz_to_mel(self.fmin, self.htk), hz_to_mel(fmax, self.htk));
+        (hz_to_mel(hz, self.htk) - low) / (high - low) * (self.n_mels + 1) as f64
+    }
+
+    /// Computes the filterbank for bins at the given frequencies (in Hz): the weight of each bin in each mel band.
+    ///
+    /// As in librosa, each filter is a triangle, normalised (Slaney style) so that its area is roughly constant.
+    pub fn filterbank(&self, frequencies: &[f64], sample_rate: u32) -> Vec<Vec<f64>> {
+        let mel_f = self.frequencies(sample_rate);
+
+        (0..self.n_mels)
+            .map(|band| {
+                let (lower, centre, upper) = (mel_f[band], mel_f[band + 1], mel_f[band + 2]);
+                let norm = 2.0 / (upper - lower);
+
+                frequencies
+                    .iter()
+                    .map(|f| {
+                        let rising = (f - lower) / (centre - lower);
+                        let falling = (upper - f) / (upper - centre);
+                        rising.min(falling).max(0.0) * norm
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl Spectrogram {
+    /// Computes a mel (power) spectrogram, with one row per mel band, and the same columns as the spectrogram.
+    pub fn mel_spectrogram(&self, mel: &Mel) -> Spectrogram {
+        let parameters = self.parameters_or_default();
+        let frequencies: Vec<f64> = (0..self.height as usize)
+            .map(|bin| self.bin_to_hz(bin))
+            .collect();
+        let filterbank = mel.filterbank(&frequencies[..], parameters.sample_rate);
+
+        let data = self
+            .columns()
+            .flat_map(|column| {
+                filterbank
+                    .iter()
+                    .map(|weights| {
+                        weights
+                            .iter()
+                            .zip(column.iter())
+                            .map(|(w, v)| w * v * v)
+                            .sum::<f64>()
+                    })
+                    .collect::<Vec<f64>>()
+            })
+            .collect();
+
+        let mut parameters = parameters;
+        parameters.fmin = mel.fmin;
+        parameters.fmax = mel.fmax.unwrap_or(parameters.sample_rate as f64 / 2.0);
+        parameters.bins_per_octave = 0;
+        parameters.scaling = "mel_power".to_string();
+        parameters.mel_scale = if mel.htk { "htk" } else { "slaney" }.to_string();
+
+        Spectrogram {
+            width: self.width,
+            height: mel.n_mels as u32,
+            data,
+            parameters: Some(parameters),
+            ..self.without_data()
+        }
+    }
+}
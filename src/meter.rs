@@ -0,0 +1,186 @@
+/*!
+ * Downbeat detection, and grouping of beats into bars, phrases and choruses.
+ *
+ * Given a beat grid, the beats are grouped into bars by finding the downbeats, and the bars into phrases (of 8 bars, by default), and the phrases into choruses (of 4 phrases, i.e. 32 bars, as in the AABA form of most swing tunes). Each grouping is found in the same way: new bars, phrases and choruses tend to start with a burst of spectral novelty (e.g. the onset of a new soloist, or a drum fill resolving) and a change of harmony, so the grouping chosen is the one whose boundaries have the most novelty and harmonic change on average.
+ *
+ * Spectral novelty is measured with an onset envelope (see the `onset` module), and harmonic change with a chromagram (see the `chroma` module), both computed from the same spectrogram as the beat grid:
+ *
+ * ```ignore
+ * let envelope = spectrogram.onset_strength(&Onset::default());
+ * let grid = Meter::default().analyse(&beats, &envelope, &spectrogram.chroma(None));
+ * for phrase in &grid.phrases {
+ *     println!("phrase at {:.1}s", spectrogram.frame_to_seconds(phrase.start));
+ * }
+ * ```
+ */
+use super::sync::Aggregation;
+use super::Spectrogram;
+
+use std::ops::Range;
+
+/// The number of frames either side of a boundary searched for its spectral novelty.
+const NOVELTY_RADIUS: usize = 2;
+
+/// The parameters of downbeat and phrase detection.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Meter {
+    pub beats_per_bar: usize,
+    pub bars_per_phrase: usize,
+    pub phrases_per_chorus: usize,
+    /// The weight (in [0,1]) of harmonic change, relative to spectral novelty, when scoring boundaries.
+    pub harmonic_weight: f64,
+}
+
+impl Default for Meter {
+    fn default() -> Self {
+        Meter {
+            beats_per_bar: 4,
+            bars_per_phrase: 8,
+            phrases_per_chorus: 4,
+            harmonic_weight: 0.5,
+        }
+    }
+}
+
+/// The bars, phrases and choruses of a track, as ranges of frames.
+///
+/// Beats before the first downbeat form a partial (pickup) bar, bars before the first phrase boundary a partial phrase (e.g. an intro), and so on. The last bar, phrase and chorus run to the end of the track.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BarGrid {
+    /// The frames of the downbeats.
+    pub downbeats: Vec<usize>,
+    pub bars: Vec<Range<usize>>,
+    pub phrases: Vec<Range<usize>>,
+    pub choruses: Vec<Range<usize>>,
+}
+
+impl Meter {
+    /// Finds the downbeats, bars, phrases and choruses of a track, given the frames of its beats (in increasing order), a spectral novelty curve (e.g. an onset envelope) and a chromagram, all aligned with the same frames.
+    ///
+    /// # Panics
+    /// panics if any of the group sizes are zero, or `harmonic_weight` is outside [0,1]
+    pub fn analyse(&self, beats: &[usize], novelty: &[f64], chroma: &Spectrogram) -> BarGrid {
+        assert!(self.beats_per_bar > 0 && self.bars_per_phrase > 0 && self.phrases_per_chorus > 0);
+        assert!(0.0 <= self.harmonic_weight && self.harmonic_weight <= 1.0);
+
+        let end = (chroma.width as usize).max(novelty.len());
+        let downbeats = self.group(beats, self.beats_per_bar, novelty, chroma);
+        let phrase_starts = self.group(&downbeats, self.bars_per_phrase, novelty, chroma);
+        let chorus_starts = self.group(&phrase_starts, self.phrases_per_chorus, novelty, chroma);
+
+        BarGrid {
+            bars: ranges(beats, &downbeats, end),
+            phrases: ranges(beats, &phrase_starts, end),
+            choruses: ranges(beats, &chorus_starts, end),
+            downbeats,
+        }
+    }
+
+    /// Picks every `size`th unit (starting at the best phase) as the start of a group, returning the frames of the starts.
+    fn group(
+        &self,
+        units: &[usize],
+        size: usize,
+        novelty: &[f64],
+        chroma: &Spectrogram,
+    ) -> Vec<usize> {
+        let scores = self.boundary_scores(units, novelty, chroma);
+
+        let phase = (0..size.min(units.len()))
+            .map(|phase| {
+                let scores: Vec<f64> = scores.iter().skip(phase).step_by(size).cloned().collect();
+                (phase, scores.iter().sum::<f64>() / scores.len() as f64)
+            })
+            .fold((0, f64::MIN), |best, (phase, score)| {
+                if score > best.1 {
+                    (phase, score)
+                } else {
+                    best
+                }
+            })
+            .0;
+
+        units.iter().skip(phase).step_by(size).cloned().collect()
+    }
+
+    /// Scores the start of each unit (e.g. beat) as a boundary, by the spectral novelty around it, and the harmonic change between the units either side of it.
+    fn boundary_scores(&self, units: &[usize], novelty: &[f64], chroma: &Spectrogram) -> Vec<f64> {
+        let novelty: Vec<f64> = units
+            .iter()
+            .map(|start| {
+                let window = start.saturating_sub(NOVELTY_RADIUS)..(start + NOVELTY_RADIUS + 1);
+                novelty
+                    .get(window.start..window.end.min(novelty.len()))
+                    .unwrap_or(&[])
+                    .iter()
+                    .fold(0.0f64, |m, v| m.max(*v))
+            })
+            .collect();
+
+        // The mean chroma of each unit, or zeros for units beyond the end of the chromagram
+        let synced = chroma.beat_sync(units, Aggregation::Mean);
+        let starts = chroma.beat_sync_frames(units);
+        let silence = vec![0.0; chroma.height as usize];
+        let profiles: Vec<&[f64]> = units
+            .iter()
+            .map(|start| match starts.binary_search(start) {
+                Ok(column) => synced.column(column),
+                Err(_) => &silence[..],
+            })
+            .collect();
+        let change: Vec<f64> = (0..units.len())
+            .map(|i| match i {
+                0 => 0.0,
+                _ => cosine_distance(profiles[i - 1], profiles[i]),
+            })
+            .collect();
+
+        let (novelty, change) = (max_normalise(novelty), max_normalise(change));
+        novelty
+            .iter()
+            .zip(change.iter())
+            .map(|(n, c)| (1.0 - self.harmonic_weight) * n + self.harmonic_weight * c)
+            .collect()
+    }
+}
+
+/// Splits the track into ranges at each of `starts`, with a partial range from the first beat to the first start, if they differ.
+fn ranges(beats: &[usize], starts: &[usize], end: usize) -> Vec<Range<usize>> {
+    let mut boundaries = Vec::with_capacity(starts.len() + 2);
+    match (beats.first(), starts.first()) {
+        (Some(first_beat), Some(first_start)) if first_beat < first_start => {
+            boundaries.push(*first_beat)
+        }
+        _ => {}
+    }
+    boundaries.extend_from_slice(starts);
+    boundaries.push(end);
+
+    boundaries
+        .windows(2)
+        .filter(|b| b[0] < b[1])
+        .map(|b| b[0]..b[1])
+        .collect()
+}
+
+/// One minus the cosine similarity of two vectors, or 0 if either is all zeros.
+fn cosine_distance(a: &[f64], b: &[f64]) -> f64 {
+    let dot: f64 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
+    let norms =
+        a.iter().map(|x| x * x).sum::<f64>().sqrt() * b.iter().map(|y| y * y).sum::<f64>().sqrt();
+    if norms > 0.0 {
+        1.0 - dot / norms
+    } else {
+        0.0
+    }
+}
+
+fn max_normalise(mut values: Vec<f64>) -> Vec<f64> {
+    let max = values.iter().fold(0.0f64, |m, v| m.max(*v));
+    if max > 0.0 {
+        values.iter_mut().for_each(|v| *v /= max);
+    }
+    values
+}
@@ -0,0 +1,236 @@
+/*!
+ * Mel-frequency cepstral coefficients (MFCCs) and delta features, equivalent to `librosa.feature.mfcc` and `librosa.feature.delta`.
+ *
+ * MFCCs are the DCT-II of a log (dB) mel spectrogram, and compactly describe the timbre of each frame. As with mel spectrograms, they should be computed from a spectrogram with `Scaling::Magnitude` and `Normalisation::None`:
+ *
+ * ```ignore
+ * let spectrogram = SpectrogramBuilder::new()
+ *     .scaling(Scaling::Magnitude)
+ *     .normalisation(Normalisation::None)
+ *     .build(&samples);
+ * let mfcc = spectrogram.mfcc(&Mfcc::default());
+ * let (d1, d2) = (mfcc.delta(9, 1), mfcc.delta(9, 2));
+ * ```
+ *
+ * The rows of the returned spectrograms are coefficients, rather than frequency bins.
+ */
+use super::db::{self, Reference, POWER_AMIN};
+use super::mel::Mel;
+use super::{Spectrogram, TOP_DB};
+
+/// The parameters of an MFCC computation. The default parameters are those of `librosa.feature.mfcc`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mfcc {
+    /// The number of coefficients to return.
+    pub n_mfcc: usize,
+    /// The mel filterbank to compute the log mel spectrogram with.
+    pub mel: Mel,
+    /// The cepstral liftering coefficient, or 0 to disable liftering.
+    pub lifter: f64,
+}
+
+impl Default for Mfcc {
+    fn default() -> Self {
+        Mfcc {
+            n_mfcc: 20,
+            mel: Mel::default(),
+            lifter: 0.0,
+        }
+    }
+}
+
+impl Spectrogram {
+    /// Computes MFCCs, with one row per coefficient, and the same columns as the spectrogram.
+    ///
+    /// As in librosa, the mel spectrogram is converted to dB relative to 1, with an `amin` of `1e-10` and a `top_db` of 80, and the DCT is orthonormal.
+    ///
+    /// # Panics
+    /// panics if `n_mfcc` is larger than the number of mel bands
+    pub fn mfcc(&self, mfcc: &Mfcc) -> Spectrogram {
+        let n_mels = mfcc.mel.n_mels;
+        assert!(mfcc.n_mfcc <= n_mels);
+
+        let mut log_mel = self.mel_spectrogram(&mfcc.mel);
+        db::power_to_db(
+            &mut log_mel.data[..],
+            &Reference::Value(1.0),
+            POWER_AMIN,
+            Some(TOP_DB),
+        );
+
+        let basis = dct_basis(n_mels, mfcc.n_mfcc);
+        let lifter: Vec<f64> = (0..mfcc.n_mfcc)
+            .map(|n| {
+                if mfcc.lifter > 0.0 {
+                    1.0 + (mfcc.lifter / 2.0)
+                        * (std::f64::consts::PI * (n + 1) as f64 / mfcc.lifter).sin()
+                } else {
+                    1.0
+                }
+            })
+            .collect();
+
+        let data = log_mel
+            .columns()
+            .flat_map(|column| {
+                basis
+                    .iter()
+                    .zip(lifter.iter())
+                    .map(|(b, l)| l * b.iter().zip(column).map(|(x, y)| x * y).sum::<f64>())
+                    .collect::<Vec<f64>>()
+            })
+            .collect();
+
+        let mut parameters = log_mel.parameters_or_default();
+        parameters.scaling = "mfcc".to_string();
+        parameters.mel_scale = String::new();
+
+        Spectrogram {
+            width: self.width,
+            height: mfcc.n_mfcc as u32,
+            data,
+            parameters: Some(parameters),
+            ..self.without_data()
+        }
+    }
+
+    /// Computes the delta features of order `order` (1 for deltas, 2 for delta-deltas) of each row, as `librosa.feature.delta`.
+    ///
+    /// Each row is smoothed and differentiated with a Savitzky-Golay filter of `width` frames. At the edges, where the filter doesn't fit, a polynomial is fitted to the first (or last) `width` frames instead, as in scipy's "interp" mode.
+    ///
+    /// # Panics
+    /// panics if `width` is even, less than 3, or more than the width of the spectrogram, or if `order` is 0
+    pub fn delta(&self, width: usize, order: usize) -> Spectrogram {
+        assert!(
+            width >= 3 && width % 2 == 1,
+            "width must be an odd integer >= 3"
+        );
+        assert!(
+            width <= self.width as usize,
+            "width must be at most the number of frames"
+        );
+        assert!(order > 0, "order must be positive");
+
+        let frames = self.width as usize;
+        let height = self.height as usize;
+        let half = width / 2;
+
+        // Interior frames all use the same weights, centred on the frame
+        let positions: Vec<f64> = (0..width).map(|i| i as f64).collect();
+        let centre = savgol_weights(&positions[..], order, order, half as f64);
+        // Frames at the edges use weights for a position off centre
+        let edges: Vec<Vec<f64>> = (0..width)
+            .map(|x| savgol_weights(&positions[..], order, order, x as f64))
+            .collect();
+
+        let mut data = vec![0.0; frames * height];
+        for frame in 0..frames {
+            let (start, weights) = if frame < half {
+                (0, &edges[frame])
+            } else if frame + half >= frames {
+                (frames - width, &edges[frame + width - frames])
+            } else {
+                (frame - half, &centre)
+            };
+
+            for bin in 0..height {
+                data[frame * height + bin] = weights
+                    .iter()
+                    .enumerate()
+                    .map(|(i, w)| w * self.data[(start + i) * height + bin])
+                    .sum();
+            }
+        }
+
+        Spectrogram {
+            width: self.width,
+            height: self.height,
+            data,
+            ..self.without_data()
+        }
+    }
+}
+
+/// The first `n` rows of the orthonormal DCT-II matrix of size `size`.
+fn dct_basis(size: usize, n: usize) -> Vec<Vec<f64>> {
+    (0..n)
+        .map(|k| {
+            let scale = if k == 0 {
+                (1.0 / size as f64).sqrt()
+            } else {
+                (2.0 / size as f64).sqrt()
+            };
+            (0..size)
+                .map(|i| {
+                    scale
+                        * (std::f64::consts::PI * k as f64 * (2 * i + 1) as f64 / (2 * size) as f64)
+                            .cos()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// The weights that compute the `deriv`th derivative at `x` of the least squares polynomial of degree `degree` fitted to values at `positions`.
+fn savgol_weights(positions: &[f64], degree: usize, deriv: usize, x: f64) -> Vec<f64> {
+    let terms = degree + 1;
+
+    // The normal equations of the fit: (A^T A) a = A^T y, where A[i][j] = positions[i]^j
+    let mut ata = vec![vec![0.0; terms]; terms];
+    for p in positions {
+        for (j, row) in ata.iter_mut().enumerate() {
+            for (k, v) in row.iter_mut().enumerate() {
+                *v += p.powi((j + k) as i32);
+            }
+        }
+    }
+
+    // The derivative of the polynomial at x, as a linear function of its coefficients
+    let c: Vec<f64> = (0..terms)
+        .map(|j| {
+            if j < deriv {
+                0.0
+            } else {
+                let falling: f64 = ((j - deriv + 1)..=j).map(|f| f as f64).product();
+                falling * x.powi((j - deriv) as i32)
+            }
+        })
+        .collect();
+
+    // The weights are A (A^T A)^-1 c, as A^T A is symmetric
+    let z = solve(ata, c);
+    positions
+        .iter()
+        .map(|p| (0..terms).map(|j| z[j] * p.powi(j as i32)).sum())
+        .collect()
+}
+
+/// Solves a small linear system by Gaussian elimination with partial pivoting.
+fn solve(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Vec<f64> {
+    let n = b.len();
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|i, j| a[*i][col].abs().partial_cmp(&a[*j][col].abs()).unwrap())
+            .unwrap();
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+
+        let (pivot_rows, rows) = a.split_at_mut(col + 1);
+        let pivot_row = &pivot_rows[col];
+        for (offset, row) in rows.iter_mut().enumerate() {
+            let factor = row[col] / pivot_row[col];
+            for (x, p) in row[col..].iter_mut().zip(pivot_row[col..].iter()) {
+                *x -= factor * p;
+            }
+            b[col + 1 + offset] -= factor * b[col];
+        }
+    }
+
+    let mut x = vec![0.0; n];
+    for row in (0..n).rev() {
+        let sum: f64 = ((row + 1)..n).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - sum) / a[row][row];
+    }
+    x
+}
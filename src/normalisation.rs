@@ -0,0 +1,163 @@
+/*!
+ * Normalisation of spectrogram values after the dB conversion.
+ *
+ * By default, `from_buffer` rescales each spectrogram by its own minimum and maximum, so the meaning of a value depends on the track it came from. `Normalisation::FixedRange` instead maps a fixed range of dB values onto [0,1], so that values are comparable across tracks:
+ *
+ * ```ignore
+ * let spectrogram = SpectrogramBuilder::new()
+ *     .reference(Reference::Value(1.0))
+ *     .normalisation(Normalisation::FixedRange { min_db: -80.0, max_db: 0.0 })
+ *     .build(&samples);
+ * ```
+ *
+ * Spectrograms that weren't normalised when they were computed, and derived features such as tempograms (see the `tempogram` module), can be normalised for rendering with `Spectrogram::normalised`.
+ */
+use super::Spectrogram;
+
+/// How spectrogram values are normalised after the dB conversion.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Normalisation {
+    /// Leave the dB values as they are.
+    None,
+    /// Rescale the values so that the minimum maps to 0, and the maximum to 1.
+    MinMax,
+    /// Rescale the absolute values so that the minimum maps to 1, and the maximum to 0. This is the normalisation used by `from_buffer`, which (as dB values relative to the peak are never positive) maps the quietest value to 0 and the loudest to 1.
+    #[default]
+    InvertedMinMax,
+    /// Map the dB range `min_db..max_db` onto [0,1], clipping values outside of it.
+    FixedRange { min_db: f64, max_db: f64 },
+    /// Standardise each frequency bin (row) to have zero mean and unit variance.
+    PerBin,
+    /// Rescale each frame (column) so that its minimum maps to 0, and its maximum to 1.
+    PerFrame,
+    /// Clip the values to the range between the `low` and `high` percentiles (in [0,100]), and rescale that range onto [0,1].
+    Percentile { low: f64, high: f64 },
+}
+
+impl std::fmt::Display for Normalisation {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Normalisation::None => write!(formatter, "none"),
+            Normalisation::MinMax => write!(formatter, "minmax"),
+            Normalisation::InvertedMinMax => write!(formatter, "minmax_inverted"),
+            Normalisation::FixedRange { min_db, max_db } => {
+                write!(formatter, "fixed({},{})", min_db, max_db)
+            }
+            Normalisation::PerBin => write!(formatter, "per_bin"),
+            Normalisation::PerFrame => write!(formatter, "per_frame"),
+            Normalisation::Percentile { low, high } => {
+                write!(formatter, "percentile({},{})", low, high)
+            }
+        }
+    }
+}
+
+impl Normalisation {
+    /// Normalises (column major) spectrogram data with `height` frequency bins in place.
+    ///
+    /// # Panics
+    /// panics if `height` is zero, or (for `Percentile`) the percentiles are out of order or outside [0,100]
+    pub fn apply(&self, buffer: &mut [f64], height: usize) {
+        assert!(height > 0);
+
+        match *self {
+            Normalisation::None => {}
+            Normalisation::MinMax => {
+                let (min, max) = min_max(buffer.iter().cloned());
+                rescale(buffer, min, max);
+            }
+            Normalisation::InvertedMinMax => {
+                let (min, max) = min_max(buffer.iter().map(|v| v.abs()));
+
+                let range = max - min;
+                buffer
+                    .iter_mut()
+                    .for_each(|v| *v = 1.0 - ((v.abs() - min) / range));
+            }
+            Normalisation::FixedRange { min_db, max_db } => {
+                rescale(buffer, min_db, max_db);
+                clip(buffer, 0.0, 1.0);
+            }
+            Normalisation::PerBin => {
+                let frames = buffer.len() / height;
+                for bin in 0..height {
+                    let values = || buffer[bin..].iter().step_by(height);
+                    let mean = values().sum::<f64>() / frames as f64;
+                    let variance =
+                        values().map(|v| (v - mean) * (v - mean)).sum::<f64>() / frames as f64;
+                    let std = variance.sqrt();
+
+                    buffer[bin..]
+                        .iter_mut()
+                        .step_by(height)
+                        .for_each(|v| *v = if std > 0.0 { (*v - mean) / std } else { 0.0 });
+                }
+            }
+            Normalisation::PerFrame => {
+                for column in buffer.chunks_mut(height) {
+                    let (min, max) = min_max(column.iter().cloned());
+                    rescale(column, min, max);
+                }
+            }
+            Normalisation::Percentile { low, high } => {
+                assert!(0.0 <= low && low <= high && high <= 100.0);
+                let (min, max) = (percentile(buffer, low), percentile(buffer, high));
+                clip(buffer, min, max);
+                rescale(buffer, min, max);
+            }
+        }
+    }
+}
+
+impl Spectrogram {
+    /// A copy of the spectrogram with its values normalised, e.g. into [0,1] for rendering with `as_image_col`.
+    ///
+    /// The normalisation is recorded in the copy's `parameters`.
+    pub fn normalised(&self, normalisation: &Normalisation) -> Spectrogram {
+        let mut data = self.data.clone();
+        if self.height > 0 {
+            normalisation.apply(&mut data[..], self.height as usize);
+        }
+
+        let mut parameters = self.parameters_or_default();
+        parameters.normalisation = normalisation.to_string();
+        Spectrogram {
+            width: self.width,
+            height: self.height,
+            data,
+            parameters: Some(parameters),
+            ..self.without_data()
+        }
+    }
+}
+
+fn min_max<I: Iterator<Item = f64>>(values: I) -> (f64, f64) {
+    values.fold((f64::MAX, f64::MIN), |(mi, ma), x| (mi.min(x), ma.max(x)))
+}
+
+/// Linearly maps `min..max` onto [0,1]. If `min` and `max` are equal, everything maps to 0.
+fn rescale(buffer: &mut [f64], min: f64, max: f64) {
+    let range = max - min;
+    buffer
+        .iter_mut()
+        .for_each(|v| *v = if range > 0.0 { (*v - min) / range } else { 0.0 });
+}
+
+fn clip(buffer: &mut [f64], min: f64, max: f64) {
+    buffer.iter_mut().for_each(|v| *v = v.max(min).min(max));
+}
+
+/// The `q`th percentile of some values, linearly interpolated as in `np.percentile`.
+fn percentile(values: &[f64], q: f64) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+    let position = q / 100.0 * (sorted.len() - 1) as f64;
+    let (below, above) = (position.floor() as usize, position.ceil() as usize);
+    let fraction = position - below as f64;
+    sorted[below] + (sorted[above] - sorted[below]) * fraction
+}
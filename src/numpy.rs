@@ -0,0 +1,482 @@
+/*!
+ * NumPy `.npy` and `.npz` export and import.
+ *
+ * Spectrograms are written as `(frequency, time)` shaped arrays of doubles, i.e. with the same layout as `librosa.stft`, so that `np.load("track.npy")` can be compared directly against librosa's output. Tizol stores spectrograms column by column, which is exactly Fortran ordering for a `(height, width)` array, so the data is written without any reordering.
+ *
+ * An `.npz` archive additionally holds the parameters the spectrogram was computed with as (zero dimensional) arrays, along with the frequency of each row and the time of each column:
+ *
+ * ```python
+ * npz = np.load("track.npz")
+ * S, freqs, times = npz["data"], npz["frequencies"], npz["times"]
+ * sr = int(npz["sample_rate"])
+ * ```
+ */
+use super::{Parameters, Spectrogram};
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
+use std::path::Path;
+
+extern crate zip;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+const NPY_MAGIC: &[u8; 6] = b"\x93NUMPY";
+
+/// The alignment (in bytes) of the start of the array data, as used by NumPy.
+const NPY_ALIGNMENT: usize = 64;
+
+impl Spectrogram {
+    /// Saves the spectrogram as an `.npy` file at `path`.
+    pub fn save_npy<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let file = File::create(path)?;
+        self.write_npy(BufWriter::new(file))
+    }
+
+    /// Writes the spectrogram as a `(height, width)` array of doubles in `.npy` format.
+    pub fn write_npy<W: Write>(&self, w: W) -> io::Result<()> {
+        NpyArray::from_f64(
+            vec![self.height as usize, self.width as usize],
+            true,
+            &self.data[..],
+        )
+        .write(w)
+    }
+
+    /// Loads a spectrogram from an `.npy` file at `path`.
+    pub fn load_npy<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        Self::read_npy(BufReader::new(file))
+    }
+
+    /// Reads a spectrogram from a two dimensional `(frequency, time)` array in `.npy` format.
+    ///
+    /// Both C and Fortran ordered arrays of little endian floats or doubles are supported.
+    pub fn read_npy<R: Read>(r: R) -> io::Result<Self> {
+        let array = NpyArray::read(r)?;
+        if array.shape.len() != 2 {
+            return Err(invalid_data("spectrogram arrays must be two dimensional"));
+        }
+        let (height, width) = (array.shape[0], array.shape[1]);
+
+        let values = array.to_f64()?;
+        let data = if array.fortran_order {
+            values
+        } else {
+            // Row major, so transpose into columns
+            (0..width)
+                .flat_map(|c| (0..height).map(move |r| r * width + c))
+                .map(|ix| values[ix])
+                .collect()
+        };
+
+        Ok(Spectrogram {
+            width: width as u32,
+            height: height as u32,
+            data,
+            ..Default::default()
+        })
+    }
+
+    /// Saves the spectrogram, and its parameters, as an `.npz` archive at `path`.
+    pub fn save_npz<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let file = File::create(path)?;
+        self.write_npz(BufWriter::new(file))
+    }
+
+    /// Writes the spectrogram as an (uncompressed) `.npz` archive, as written by `np.savez`.
+    ///
+    /// The archive contains the spectrogram as `data`, the frequency (in Hz) of each row as `frequencies`, and the time (in seconds) of the start of each column as `times`. Each of the spectrogram's `parameters` is stored as a zero dimensional array with the same name, using the defaults of `from_buffer` if the spectrogram has no parameters.
+    pub fn write_npz<W: Write + Seek>(&self, w: W) -> io::Result<()> {
+        let parameters = self.parameters_or_default();
+
+        let frequencies: Vec<f64> = (0..self.height as usize)
+            .map(|bin| self.bin_to_hz(bin))
+            .collect();
+        let times: Vec<f64> = (0..self.width as usize)
+            .map(|frame| self.frame_to_seconds(frame))
+            .collect();
+
+        let mut arrays = vec![
+            (
+                "data",
+                NpyArray::from_f64(
+                    vec![self.height as usize, self.width as usize],
+                    true,
+                    &self.data[..],
+                ),
+            ),
+            (
+                "frequencies",
+                NpyArray::from_f64(vec![frequencies.len()], false, &frequencies[..]),
+            ),
+            (
+                "times",
+                NpyArray::from_f64(vec![times.len()], false, &times[..]),
+            ),
+        ];
+        arrays.extend(vec![
+            (
+                "sample_rate",
+                NpyArray::scalar_i64(parameters.sample_rate as i64),
+            ),
+            ("window", NpyArray::scalar_str(&parameters.window)),
+            (
+                "window_size",
+                NpyArray::scalar_i64(parameters.window_size as i64),
+            ),
+            (
+                "step_size",
+                NpyArray::scalar_i64(parameters.step_size as i64),
+            ),
+            ("fft_size", NpyArray::scalar_i64(parameters.fft_size as i64)),
+            (
+                "db_reference",
+                NpyArray::scalar_str(&parameters.db_reference),
+            ),
+            ("amin", NpyArray::scalar_f64(parameters.amin)),
+            ("top_db", NpyArray::scalar_f64(parameters.top_db)),
+            (
+                "normalisation",
+                NpyArray::scalar_str(&parameters.normalisation),
+            ),
+            ("fmin", NpyArray::scalar_f64(parameters.fmin)),
+            ("fmax", NpyArray::scalar_f64(parameters.fmax)),
+            ("scaling", NpyArray::scalar_str(&parameters.scaling)),
+            (
+                "bins_per_octave",
+                NpyArray::scalar_i64(parameters.bins_per_octave as i64),
+            ),
+            (
+                "filter_scale",
+                NpyArray::scalar_f64(parameters.filter_scale),
+            ),
+            ("component", NpyArray::scalar_str(&parameters.component)),
+            ("aggregation", NpyArray::scalar_str(&parameters.aggregation)),
+            ("mel_scale", NpyArray::scalar_str(&parameters.mel_scale)),
+        ]);
+
+        let mut zip = ZipWriter::new(w);
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        for (name, array) in arrays {
+            zip.start_file(format!("{}.npy", name), options)
+                .map_err(zip_error)?;
+            array.write(&mut zip)?;
+        }
+        zip.finish().map_err(zip_error)?;
+        Ok(())
+    }
+
+    /// Loads a spectrogram, and its parameters, from an `.npz` archive at `path`.
+    pub fn load_npz<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        Self::read_npz(BufReader::new(file))
+    }
+
+    /// Reads a spectrogram from an `.npz` archive (compressed or not) containing a `data` array.
+    ///
+    /// Any parameters stored alongside the data (as written by `write_npz`) are restored; if none are present, the spectrogram has no `parameters`.
+    pub fn read_npz<R: Read + Seek>(r: R) -> io::Result<Self> {
+        let mut zip = ZipArchive::new(r).map_err(zip_error)?;
+
+        let mut spectrogram = {
+            let data = zip.by_name("data.npy").map_err(zip_error)?;
+            Self::read_npy(data)?
+        };
+
+        let mut read = |name: &str| -> io::Result<Option<NpyArray>> {
+            match zip.by_name(&format!("{}.npy", name)) {
+                Ok(file) => NpyArray::read(file).map(Some),
+                Err(zip::result::ZipError::FileNotFound) => Ok(None),
+                Err(e) => Err(zip_error(e)),
+            }
+        };
+
+        // Only restore parameters if the archive looks like it was written by tizol
+        if let Some(sample_rate) = read("sample_rate")? {
+            let mut parameters = Parameters {
+                sample_rate: sample_rate.to_scalar_f64()? as u32,
+                ..Default::default()
+            };
+            if let Some(a) = read("window")? {
+                parameters.window = a.to_scalar_str()?;
+            }
+            if let Some(a) = read("window_size")? {
+                parameters.window_size = a.to_scalar_f64()? as u32;
+            }
+            if let Some(a) = read("step_size")? {
+                parameters.step_size = a.to_scalar_f64()? as u32;
+            }
+            if let Some(a) = read("fft_size")? {
+                parameters.fft_size = a.to_scalar_f64()? as u32;
+            }
+            if let Some(a) = read("db_reference")? {
+                parameters.db_reference = a.to_scalar_str()?;
+            }
+            if let Some(a) = read("amin")? {
+                parameters.amin = a.to_scalar_f64()?;
+            }
+            if let Some(a) = read("top_db")? {
+                parameters.top_db = a.to_scalar_f64()?;
+            }
+            if let Some(a) = read("normalisation")? {
+                parameters.normalisation = a.to_scalar_str()?;
+            }
+            if let Some(a) = read("fmin")? {
+                parameters.fmin = a.to_scalar_f64()?;
+            }
+            if let Some(a) = read("fmax")? {
+                parameters.fmax = a.to_scalar_f64()?;
+            }
+            if let Some(a) = read("scaling")? {
+                parameters.scaling = a.to_scalar_str()?;
+            }
+            if let Some(a) = read("bins_per_octave")? {
+                parameters.bins_per_octave = a.to_scalar_f64()? as u32;
+            }
+            if let Some(a) = read("filter_scale")? {
+                parameters.filter_scale = a.to_scalar_f64()?;
+            }
+            if let Some(a) = read("component")? {
+                parameters.component = a.to_scalar_str()?;
+            }
+            if let Some(a) = read("aggregation")? {
+                parameters.aggregation = a.to_scalar_str()?;
+            }
+            if let Some(a) = read("mel_scale")? {
+                parameters.mel_scale = a.to_scalar_str()?;
+            }
+            spectrogram.parameters = Some(parameters);
+        }
+
+        Ok(spectrogram)
+    }
+}
+
+/// A raw NumPy array: its dtype descriptor, ordering, shape and little endian data.
+struct NpyArray {
+    descr: String,
+    fortran_order: bool,
+    shape: Vec<usize>,
+    data: Vec<u8>,
+}
+
+impl NpyArray {
+    fn from_f64(shape: Vec<usize>, fortran_order: bool, values: &[f64]) -> Self {
+        NpyArray {
+            descr: "<f8".to_string(),
+            fortran_order,
+            shape,
+            data: values
+                .iter()
+                .flat_map(|v| v.to_le_bytes().to_vec())
+                .collect(),
+        }
+    }
+
+    fn scalar_f64(value: f64) -> Self {
+        Self::from_f64(vec![], false, &[value])
+    }
+
+    fn scalar_i64(value: i64) -> Self {
+        NpyArray {
+            descr: "<i8".to_string(),
+            fortran_order: false,
+            shape: vec![],
+            data: value.to_le_bytes().to_vec(),
+        }
+    }
+
+    fn scalar_str(value: &str) -> Self {
+        // NumPy strings are fixed width UTF-32, with at least one character
+        let chars: Vec<char> = value.chars().collect();
+        NpyArray {
+            descr: format!("<U{}", chars.len().max(1)),
+            fortran_order: false,
+            shape: vec![],
+            data: if chars.is_empty() {
+                vec![0; 4]
+            } else {
+                chars
+                    .iter()
+                    .flat_map(|c| (*c as u32).to_le_bytes().to_vec())
+                    .collect()
+            },
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    /// Converts the elements of a floating point (or integer) array to doubles.
+    fn to_f64(&self) -> io::Result<Vec<f64>> {
+        let values: Vec<f64> = match &self.descr[..] {
+            "<f8" => self
+                .data
+                .chunks(8)
+                .map(|b| f64::from_le_bytes(le_bytes8(b)))
+                .collect(),
+            "<f4" => self
+                .data
+                .chunks(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64)
+                .collect(),
+            "<i8" => self
+                .data
+                .chunks(8)
+                .map(|b| i64::from_le_bytes(le_bytes8(b)) as f64)
+                .collect(),
+            "<i4" => self
+                .data
+                .chunks(4)
+                .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64)
+                .collect(),
+            descr => return Err(invalid_data(&format!("unsupported dtype {}", descr))),
+        };
+
+        if values.len() != self.len() {
+            return Err(invalid_data("array data does not match its shape"));
+        }
+        Ok(values)
+    }
+
+    fn to_scalar_f64(&self) -> io::Result<f64> {
+        match &self.to_f64()?[..] {
+            [v] => Ok(*v),
+            _ => Err(invalid_data("expected a scalar array")),
+        }
+    }
+
+    fn to_scalar_str(&self) -> io::Result<String> {
+        if !self.descr.starts_with("<U") || self.len() != 1 {
+            return Err(invalid_data("expected a scalar string array"));
+        }
+        Ok(self
+            .data
+            .chunks(4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .take_while(|c| *c != 0)
+            .filter_map(std::char::from_u32)
+            .collect())
+    }
+
+    fn write<W: Write>(&self, mut w: W) -> io::Result<()> {
+        let shape = match self.shape.len() {
+            0 => "()".to_string(),
+            1 => format!("({},)", self.shape[0]),
+            _ => format!(
+                "({})",
+                self.shape
+                    .iter()
+                    .map(|d| d.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+        let mut header = format!(
+            "{{'descr': '{}', 'fortran_order': {}, 'shape': {}, }}",
+            self.descr,
+            if self.fortran_order { "True" } else { "False" },
+            shape
+        );
+
+        // Pad the header with spaces (and a final newline) so that the data is aligned
+        let preamble = NPY_MAGIC.len() + 2 + 2;
+        let unpadded = preamble + header.len() + 1;
+        let padding = (NPY_ALIGNMENT - unpadded % NPY_ALIGNMENT) % NPY_ALIGNMENT;
+        header.extend(std::iter::repeat_n(' ', padding));
+        header.push('\n');
+
+        w.write_all(NPY_MAGIC)?;
+        // Format version 1.0
+        w.write_all(&[1, 0])?;
+        w.write_all(&(header.len() as u16).to_le_bytes())?;
+        w.write_all(header.as_bytes())?;
+        w.write_all(&self.data[..])?;
+        w.flush()
+    }
+
+    fn read<R: Read>(mut r: R) -> io::Result<Self> {
+        let mut magic = [0; 8];
+        r.read_exact(&mut magic)?;
+        if &magic[..6] != NPY_MAGIC {
+            return Err(invalid_data("not a .npy file"));
+        }
+
+        // Version 1.0 uses a u16 header length, later versions a u32
+        let header_len = if magic[6] == 1 {
+            let mut len = [0; 2];
+            r.read_exact(&mut len)?;
+            u16::from_le_bytes(len) as usize
+        } else {
+            let mut len = [0; 4];
+            r.read_exact(&mut len)?;
+            u32::from_le_bytes(len) as usize
+        };
+
+        let mut header = vec![0; header_len];
+        r.read_exact(&mut header[..])?;
+        let header = String::from_utf8(header).map_err(|_| invalid_data("malformed header"))?;
+
+        let descr = header_value(&header, "descr")
+            .map(|v| v.trim_matches(|c| c == '\'' || c == '"').to_string())
+            .ok_or_else(|| invalid_data("header is missing 'descr'"))?;
+        let fortran_order = header_value(&header, "fortran_order")
+            .map(|v| v == "True")
+            .ok_or_else(|| invalid_data("header is missing 'fortran_order'"))?;
+        let shape = header_value(&header, "shape")
+            .ok_or_else(|| invalid_data("header is missing 'shape'"))?
+            .trim_matches(|c| c == '(' || c == ')')
+            .split(',')
+            .map(|d| d.trim())
+            .filter(|d| !d.is_empty())
+            .map(|d| d.parse::<usize>())
+            .collect::<Result<Vec<usize>, _>>()
+            .map_err(|_| invalid_data("malformed shape"))?;
+
+        // Accept native (`=`) and unspecified (`|`) byte orders as little endian
+        let descr = descr.replacen('=', "<", 1).replacen('|', "<", 1);
+        if descr.starts_with('>') {
+            return Err(invalid_data("big endian arrays are not supported"));
+        }
+
+        let mut data = Vec::new();
+        r.read_to_end(&mut data)?;
+
+        Ok(NpyArray {
+            descr,
+            fortran_order,
+            shape,
+            data,
+        })
+    }
+}
+
+/// Finds the (unparsed) value of `key` in a NumPy header dictionary.
+fn header_value<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    let start = header.find(&format!("'{}'", key))? + key.len() + 2;
+    let rest = header[start..].trim_start().strip_prefix(':')?.trim_start();
+
+    // The shape is a tuple, which contains commas of its own
+    let end = if rest.starts_with('(') {
+        rest.find(')')? + 1
+    } else {
+        rest.find([',', '}'])?
+    };
+    Some(rest[..end].trim())
+}
+
+fn le_bytes8(b: &[u8]) -> [u8; 8] {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(b);
+    bytes
+}
+
+fn zip_error(e: zip::result::ZipError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
@@ -0,0 +1,73 @@
+/*!
+ * Onset strength envelopes, equivalent to `librosa.onset.onset_strength`.
+ *
+ * The onset strength of a frame is the spectral flux of a log (dB) mel spectrogram: the mean, over every mel band, of the increase in energy since `lag` frames earlier. Peaks in the envelope are note onsets, and its periodicity is the basis of the tempograms in the `tempogram` module.
+ *
+ * As with mel spectrograms, onset strength should be computed from a spectrogram with `Scaling::Magnitude` and `Normalisation::None`:
+ *
+ * ```ignore
+ * let spectrogram = SpectrogramBuilder::new()
+ *     .scaling(Scaling::Magnitude)
+ *     .normalisation(Normalisation::None)
+ *     .build(&samples);
+ * let envelope = spectrogram.onset_strength(&Onset::default());
+ * ```
+ */
+use super::db::{self, Reference, POWER_AMIN};
+use super::mel::Mel;
+use super::{Spectrogram, TOP_DB};
+
+/// The parameters of an onset strength envelope. The default parameters are those of `librosa.onset.onset_strength`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Onset {
+    /// The mel filterbank to compute the log mel spectrogram with.
+    pub mel: Mel,
+    /// The number of frames to compare each frame with.
+    pub lag: usize,
+}
+
+impl Default for Onset {
+    fn default() -> Self {
+        Onset {
+            mel: Mel::default(),
+            lag: 1,
+        }
+    }
+}
+
+impl Spectrogram {
+    /// The onset strength of every frame of a spectrogram of linear magnitudes.
+    ///
+    /// The envelope has one value per frame, so is aligned with the columns of the spectrogram: the value of frame `t` is the flux from frame `t - lag` to frame `t`, and the first `lag` values are zero. (librosa instead centres its frames, and shifts the envelope to match.)
+    ///
+    /// # Panics
+    /// panics if `lag` is zero
+    pub fn onset_strength(&self, onset: &Onset) -> Vec<f64> {
+        assert!(onset.lag > 0);
+
+        let mut mel = self.mel_spectrogram(&onset.mel);
+        db::power_to_db(
+            &mut mel.data[..],
+            &Reference::Value(1.0),
+            POWER_AMIN,
+            Some(TOP_DB),
+        );
+
+        let columns: Vec<&[f64]> = mel.columns().collect();
+        (0..self.width as usize)
+            .map(|t| {
+                if t < onset.lag || columns.is_empty() {
+                    return 0.0;
+                }
+                let (current, previous) = (columns[t], columns[t - onset.lag]);
+                current
+                    .iter()
+                    .zip(previous.iter())
+                    .map(|(c, p)| (c - p).max(0.0))
+                    .sum::<f64>()
+                    / current.len() as f64
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,102 @@
+/*!
+ * Per-channel energy normalisation (PCEN), equivalent to `librosa.pcen`.
+ *
+ * PCEN is an alternative to the dB conversion of `from_buffer`: each frequency bin is divided by a smoothed version of itself (an automatic gain control), and then compressed with a root function. This suppresses stationary noise - such as the hiss and crackle of old 78rpm transfers - while emphasising onsets, which makes it much more robust for onset and tempo detection.
+ *
+ * ```ignore
+ * let spectrogram = SpectrogramBuilder::new()
+ *     .pcen(Pcen::default())
+ *     .normalisation(Normalisation::MinMax)
+ *     .build(&samples);
+ * ```
+ */
+
+/// The parameters of a PCEN transform. The default values are those of `librosa.pcen`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pcen {
+    /// The exponent of the gain control (`alpha`), in [0,1].
+    pub gain: f64,
+    /// The bias added before the root compression (`delta`).
+    pub bias: f64,
+    /// The exponent of the root compression (`r`). A power of 0 uses log compression instead.
+    pub power: f64,
+    /// The time constant (in seconds) of the smoothing filter.
+    pub time_constant: f64,
+    /// A small constant, to avoid dividing by zero.
+    pub eps: f64,
+}
+
+impl Default for Pcen {
+    fn default() -> Self {
+        Pcen {
+            gain: 0.98,
+            bias: 2.0,
+            power: 0.5,
+            time_constant: 0.4,
+            eps: 1e-6,
+        }
+    }
+}
+
+impl std::fmt::Display for Pcen {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            formatter,
+            "pcen(gain={},bias={},power={},time_constant={},eps={})",
+            self.gain, self.bias, self.power, self.time_constant, self.eps
+        )
+    }
+}
+
+impl Pcen {
+    /// The coefficient of the first order IIR smoothing filter, for frames `step_size` samples apart at `sample_rate`.
+    pub fn smoothing_coefficient(&self, sample_rate: u32, step_size: usize) -> f64 {
+        let t_frames = self.time_constant * sample_rate as f64 / step_size as f64;
+        ((1.0 + 4.0 * t_frames * t_frames).sqrt() - 1.0) / (2.0 * t_frames * t_frames)
+    }
+
+    /// Applies PCEN in place to (column major) magnitude data with `height` frequency bins, whose frames are `step_size` samples apart at `sample_rate`.
+    ///
+    /// As in librosa, the smoothing filter starts in its steady state for the first frame. librosa suggests scaling floating point audio by `2^31` first, to match the range of integer audio that PCEN was designed for.
+    ///
+    /// # Panics
+    /// panics if `height` is zero, or if any of the parameters are out of range
+    pub fn apply(&self, data: &mut [f64], height: usize, sample_rate: u32, step_size: usize) {
+        assert!(height > 0);
+        assert!(
+            self.gain >= 0.0 && self.gain <= 1.0,
+            "gain must be in [0,1]"
+        );
+        assert!(self.bias >= 0.0, "bias must be non-negative");
+        assert!(self.power >= 0.0, "power must be non-negative");
+        assert!(self.time_constant > 0.0, "time_constant must be positive");
+        assert!(self.eps > 0.0, "eps must be positive");
+
+        let b = self.smoothing_coefficient(sample_rate, step_size);
+
+        // Smooth each bin over time, starting from the value of the first frame
+        let mut smooth: Vec<f64> = data.iter().take(height).map(|v| v.abs()).collect();
+
+        for column in data.chunks_mut(height) {
+            for (v, m) in column.iter_mut().zip(smooth.iter_mut()) {
+                let s = v.abs();
+                *m = (1.0 - b) * *m + b * s;
+
+                // Computed in the log domain, for numerical stability
+                let agc = (-self.gain * (self.eps.ln() + (*m / self.eps).ln_1p())).exp();
+                *v = self.compress(s * agc);
+            }
+        }
+    }
+
+    fn compress(&self, v: f64) -> f64 {
+        if self.power == 0.0 {
+            v.ln_1p()
+        } else if self.bias == 0.0 {
+            (self.power * v.ln()).exp()
+        } else {
+            self.bias.powf(self.power) * (self.power * (v / self.bias).ln_1p()).exp_m1()
+        }
+    }
+}
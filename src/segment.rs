@@ -0,0 +1,292 @@
+/*!
+ * Structural segmentation with a self-similarity matrix.
+ *
+ * Features (e.g. a chromagram, or MFCCs) are averaged between each pair of beats (see the `sync` module), and every beat is compared with every other, giving a self-similarity matrix: repeated sections, such as the heads of a tune, show up as bright off-diagonal stripes, and the boundaries between sections as the corners of bright blocks along the diagonal. Sliding a checkerboard kernel along the diagonal gives a novelty curve, whose peaks are the segment boundaries (Foote's method).
+ *
+ * The segments are then labelled: the first is the intro and the last the outro, the second is the head, and any later segment that is similar enough to the head is also labelled a head. Of the remaining segments (the solos), the loudest is labelled the shout chorus. These labels are heuristics aimed at the arrangements of swing-era big band recordings, and are only as good as the boundaries they are given.
+ *
+ * The self-similarity matrix is returned as a (square) spectrogram with values in [0,1], whose columns and rows are the beat-synchronous columns of the features, so it can be rendered like any other spectrogram:
+ *
+ * ```ignore
+ * let structure = Segmenter::default().analyse(&spectrogram.chroma(None), &beats, &builder.rms(&samples));
+ * structure.similarity.as_image_col().save("ssm.png")?;
+ * for segment in &structure.segments {
+ *     println!("{} at {:.1}s", segment.label, spectrogram.frame_to_seconds(segment.frames.start));
+ * }
+ * ```
+ */
+use super::sync::Aggregation;
+use super::Spectrogram;
+
+use std::fmt;
+use std::ops::Range;
+
+/// The `scaling` recorded in the parameters of self-similarity matrices.
+pub const SELF_SIMILARITY: &str = "self_similarity";
+
+/// The parameters of structural segmentation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Segmenter {
+    /// The width (in beats) of the checkerboard kernel. Wider kernels find longer sections.
+    pub kernel_size: usize,
+    /// The minimum length (in beats) of a segment.
+    pub min_segment_beats: usize,
+    /// The minimum (normalised) novelty of a segment boundary, in [0,1].
+    pub threshold: f64,
+    /// How similar (relative to the head's similarity to itself) a segment must be to the head to also be labelled a head.
+    pub head_similarity: f64,
+}
+
+impl Default for Segmenter {
+    fn default() -> Self {
+        Segmenter {
+            kernel_size: 32,
+            min_segment_beats: 16,
+            threshold: 0.1,
+            head_similarity: 0.9,
+        }
+    }
+}
+
+/// The label of a segment of a tune.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Section {
+    Intro,
+    Head,
+    Solo,
+    ShoutChorus,
+    Outro,
+}
+
+impl fmt::Display for Section {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Section::Intro => write!(formatter, "intro"),
+            Section::Head => write!(formatter, "head"),
+            Section::Solo => write!(formatter, "solo"),
+            Section::ShoutChorus => write!(formatter, "shout chorus"),
+            Section::Outro => write!(formatter, "outro"),
+        }
+    }
+}
+
+/// A labelled segment of a tune.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Segment {
+    /// The frames covered by the segment.
+    pub frames: Range<usize>,
+    /// The columns (and rows) of the self-similarity matrix covered by the segment.
+    pub columns: Range<usize>,
+    pub label: Section,
+}
+
+/// The structure of a tune.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Structure {
+    pub similarity: Spectrogram,
+    /// The (normalised) checkerboard novelty of each column of the self-similarity matrix.
+    pub novelty: Vec<f64>,
+    pub segments: Vec<Segment>,
+}
+
+impl Segmenter {
+    /// Segments a tune, given frame-aligned features (e.g. a chromagram), the frames of its beats (in increasing order), and the energy of each frame (e.g. `SpectrogramBuilder::rms`), which is used to find the shout chorus.
+    ///
+    /// # Panics
+    /// panics if `kernel_size` or `min_segment_beats` is zero
+    pub fn analyse(&self, features: &Spectrogram, beats: &[usize], energy: &[f64]) -> Structure {
+        assert!(self.kernel_size > 0 && self.min_segment_beats > 0);
+
+        let similarity = features
+            .beat_sync(beats, Aggregation::Mean)
+            .self_similarity();
+        let starts = features.beat_sync_frames(beats);
+        let novelty = checkerboard_novelty(&similarity, self.kernel_size);
+        let columns = similarity.width as usize;
+
+        // Segment boundaries are the peaks of the novelty curve
+        let radius = self.min_segment_beats / 2;
+        let mut boundaries: Vec<usize> = vec![0];
+        for i in 1..columns {
+            let window = &novelty[i.saturating_sub(radius)..(i + radius + 1).min(columns)];
+            let is_peak = window.iter().all(|v| *v <= novelty[i]);
+            if is_peak
+                && novelty[i] >= self.threshold
+                && i - boundaries[boundaries.len() - 1] >= self.min_segment_beats
+                && columns - i >= self.min_segment_beats
+            {
+                boundaries.push(i);
+            }
+        }
+        boundaries.push(columns);
+
+        let end = features.width as usize;
+        let mut segments: Vec<Segment> = boundaries
+            .windows(2)
+            .filter(|b| b[0] < b[1])
+            .map(|b| Segment {
+                frames: starts[b[0]]..starts.get(b[1]).cloned().unwrap_or(end),
+                columns: b[0]..b[1],
+                label: Section::Solo,
+            })
+            .collect();
+        self.label(&mut segments[..], &similarity, energy);
+
+        Structure {
+            similarity,
+            novelty,
+            segments,
+        }
+    }
+
+    fn label(&self, segments: &mut [Segment], similarity: &Spectrogram, energy: &[f64]) {
+        let count = segments.len();
+        if count >= 3 {
+            segments[0].label = Section::Intro;
+            segments[count - 1].label = Section::Outro;
+        }
+        let head = match count {
+            0 => return,
+            1 | 2 => 0,
+            _ => 1,
+        };
+        segments[head].label = Section::Head;
+
+        let head_columns = segments[head].columns.clone();
+        let self_similarity = block_mean(similarity, &head_columns, &head_columns);
+        for segment in segments.iter_mut().skip(head + 1) {
+            if segment.label == Section::Solo
+                && block_mean(similarity, &segment.columns, &head_columns)
+                    >= self.head_similarity * self_similarity
+            {
+                segment.label = Section::Head;
+            }
+        }
+
+        // The shout chorus is the loudest of (at least two) solos
+        let solos: Vec<(usize, f64)> = segments
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.label == Section::Solo)
+            .map(|(i, s)| (i, mean(energy.get(s.frames.clone()).unwrap_or(&[]))))
+            .collect();
+        if solos.len() >= 2 {
+            let (loudest, level) =
+                solos
+                    .iter()
+                    .cloned()
+                    .fold((0, f64::MIN), |best, s| if s.1 > best.1 { s } else { best });
+            if solos.iter().filter(|s| s.1 >= level).count() == 1 {
+                segments[loudest].label = Section::ShoutChorus;
+            }
+        }
+    }
+}
+
+impl Spectrogram {
+    /// The self-similarity matrix of the columns of a spectrogram (usually beat-synchronous features): the cosine similarity of every pair of columns, with negative similarities clipped to 0.
+    ///
+    /// The matrix is returned as a square spectrogram, whose `scaling` is `SELF_SIMILARITY`.
+    pub fn self_similarity(&self) -> Spectrogram {
+        let columns: Vec<&[f64]> = self.columns().collect();
+        let norms: Vec<f64> = columns
+            .iter()
+            .map(|c| c.iter().map(|v| v * v).sum::<f64>().sqrt())
+            .collect();
+
+        let width = self.width as usize;
+        let mut data = vec![0.0; width * width];
+        for i in 0..width {
+            for j in i..width {
+                let similarity = if norms[i] > 0.0 && norms[j] > 0.0 {
+                    let dot: f64 = columns[i]
+                        .iter()
+                        .zip(columns[j].iter())
+                        .map(|(a, b)| a * b)
+                        .sum();
+                    (dot / (norms[i] * norms[j])).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                data[i * width + j] = similarity;
+                data[j * width + i] = similarity;
+            }
+        }
+
+        let mut parameters = self.parameters_or_default();
+        parameters.scaling = SELF_SIMILARITY.to_string();
+        parameters.normalisation = String::new();
+        parameters.mel_scale = String::new();
+        Spectrogram {
+            width: self.width,
+            height: self.width,
+            data,
+            parameters: Some(parameters),
+            ..self.without_data()
+        }
+    }
+}
+
+/// Foote's checkerboard novelty of each column of a self-similarity matrix, normalised so that its largest value is 1.
+///
+/// The kernel is `kernel_size` columns wide, tapered with a Gaussian, and the matrix is padded with zeros at its edges.
+pub fn checkerboard_novelty(similarity: &Spectrogram, kernel_size: usize) -> Vec<f64> {
+    let n = similarity.width as usize;
+    let half = (kernel_size / 2).max(1) as isize;
+    let sigma = half as f64 / 2.0;
+
+    // Positive for pairs on the same side of the centre, negative for pairs on opposite sides
+    let weight = |a: isize, b: isize| {
+        let (x, y) = (a as f64 + 0.5, b as f64 + 0.5);
+        let taper = (-(x * x + y * y) / (2.0 * sigma * sigma)).exp();
+        if (a < 0) == (b < 0) {
+            taper
+        } else {
+            -taper
+        }
+    };
+
+    let novelty: Vec<f64> = (0..n as isize)
+        .map(|i| {
+            let mut sum = 0.0;
+            for a in -half..half {
+                for b in -half..half {
+                    let (row, column) = (i + a, i + b);
+                    if row >= 0 && column >= 0 && (row as usize) < n && (column as usize) < n {
+                        sum += weight(a, b) * similarity.data[column as usize * n + row as usize];
+                    }
+                }
+            }
+            sum.max(0.0)
+        })
+        .collect();
+
+    let max = novelty.iter().fold(0.0f64, |m, v| m.max(*v));
+    if max > 0.0 {
+        novelty.iter().map(|v| v / max).collect()
+    } else {
+        novelty
+    }
+}
+
+/// The mean similarity of a block of a self-similarity matrix.
+fn block_mean(similarity: &Spectrogram, rows: &Range<usize>, columns: &Range<usize>) -> f64 {
+    let n = similarity.height as usize;
+    let values: Vec<f64> = columns
+        .clone()
+        .flat_map(|c| rows.clone().map(move |r| similarity.data[c * n + r]))
+        .collect();
+    mean(&values[..])
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        0.0
+    } else {
+        values.iter().sum::<f64>() / values.len() as f64
+    }
+}
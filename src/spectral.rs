@@ -0,0 +1,296 @@
+/*!
+ * Per-frame spectral descriptors, equivalent to librosa's `spectral_centroid`, `spectral_bandwidth`, `spectral_rolloff`, `spectral_flatness` and `spectral_contrast`, along with whole-track aggregates of them.
+ *
+ * These describe the overall "shape" of each frame of a spectrogram - e.g. a high centroid and rolloff sound bright, a low centroid sounds muddy, and a spectrum that stops short at a low rolloff sounds lo-fi. They are computed from the values of the spectrogram as magnitudes, so for results comparable with librosa, compute the spectrogram with `Scaling::Magnitude` and `Normalisation::None`:
+ *
+ * ```ignore
+ * let spectrogram = SpectrogramBuilder::new()
+ *     .scaling(Scaling::Magnitude)
+ *     .normalisation(Normalisation::None)
+ *     .build(&samples);
+ * let summary = spectrogram.spectral_descriptors().summary();
+ * println!("centroid: {:.0}Hz", summary.centroid.mean);
+ * ```
+ */
+use super::db::{self, Reference, POWER_AMIN};
+use super::Spectrogram;
+
+/// The default roll-off percentage of `spectral_rolloff`, as in librosa.
+pub const ROLL_PERCENT: f64 = 0.85;
+
+/// The parameters of `spectral_contrast`. The default parameters are those of librosa.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Contrast {
+    /// The number of octave bands above `fmin`. There is one more band, below `fmin`.
+    pub n_bands: usize,
+    /// The upper frequency (in Hz) of the lowest band.
+    pub fmin: f64,
+    /// The proportion of each band used to measure its peak and valley.
+    pub quantile: f64,
+}
+
+impl Default for Contrast {
+    fn default() -> Self {
+        Contrast {
+            n_bands: 6,
+            fmin: 200.0,
+            quantile: 0.02,
+        }
+    }
+}
+
+/// The spectral descriptors of every frame of a spectrogram, as computed by `Spectrogram::spectral_descriptors`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpectralDescriptors {
+    /// The centroid (in Hz) of each frame.
+    pub centroid: Vec<f64>,
+    /// The bandwidth (in Hz) of each frame.
+    pub bandwidth: Vec<f64>,
+    /// The roll-off frequency (in Hz) of each frame.
+    pub rolloff: Vec<f64>,
+    /// The flatness (in [0,1]) of each frame.
+    pub flatness: Vec<f64>,
+    /// The contrast (in dB) of each octave band of each frame, as a spectrogram with one row per band.
+    pub contrast: Spectrogram,
+}
+
+/// Summary statistics of a descriptor over a whole track.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Aggregate {
+    pub mean: f64,
+    /// The (population) standard deviation.
+    pub std: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Aggregate {
+    /// Summarises some values, ignoring NaNs (e.g. the contrast of bands with no bins). All statistics are 0 if there are no other values.
+    pub fn of(values: &[f64]) -> Self {
+        let values: Vec<f64> = values.iter().cloned().filter(|v| !v.is_nan()).collect();
+        if values.is_empty() {
+            return Aggregate::default();
+        }
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let variance = values.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / n;
+        Aggregate {
+            mean,
+            std: variance.sqrt(),
+            min: values.iter().cloned().fold(f64::MAX, f64::min),
+            max: values.iter().cloned().fold(f64::MIN, f64::max),
+        }
+    }
+}
+
+/// Whole-track aggregates of the spectral descriptors of a spectrogram.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DescriptorSummary {
+    pub centroid: Aggregate,
+    pub bandwidth: Aggregate,
+    pub rolloff: Aggregate,
+    pub flatness: Aggregate,
+    /// The contrast of each octave band.
+    pub contrast: Vec<Aggregate>,
+}
+
+impl SpectralDescriptors {
+    /// Summarises each descriptor over the whole track.
+    pub fn summary(&self) -> DescriptorSummary {
+        DescriptorSummary {
+            centroid: Aggregate::of(&self.centroid[..]),
+            bandwidth: Aggregate::of(&self.bandwidth[..]),
+            rolloff: Aggregate::of(&self.rolloff[..]),
+            flatness: Aggregate::of(&self.flatness[..]),
+            contrast: (0..self.contrast.height as usize)
+                .map(|band| Aggregate::of(&self.contrast.row(band).collect::<Vec<f64>>()[..]))
+                .collect(),
+        }
+    }
+}
+
+impl Spectrogram {
+    /// Computes all of the spectral descriptors of each frame, with their default parameters.
+    pub fn spectral_descriptors(&self) -> SpectralDescriptors {
+        SpectralDescriptors {
+            centroid: self.spectral_centroid(),
+            bandwidth: self.spectral_bandwidth(),
+            rolloff: self.spectral_rolloff(ROLL_PERCENT),
+            flatness: self.spectral_flatness(),
+            contrast: self.spectral_contrast(&Contrast::default()),
+        }
+    }
+
+    /// The centroid (in Hz) of each frame: the mean of the bin frequencies, weighted by magnitude. Silent frames have a centroid of 0.
+    pub fn spectral_centroid(&self) -> Vec<f64> {
+        let frequencies = self.frequencies();
+        self.columns()
+            .map(|column| {
+                let total: f64 = column.iter().sum();
+                if total <= 0.0 {
+                    return 0.0;
+                }
+                column
+                    .iter()
+                    .zip(frequencies.iter())
+                    .map(|(v, f)| v * f)
+                    .sum::<f64>()
+                    / total
+            })
+            .collect()
+    }
+
+    /// The (second order) bandwidth (in Hz) of each frame: the magnitude weighted standard deviation of the bin frequencies around the centroid.
+    pub fn spectral_bandwidth(&self) -> Vec<f64> {
+        let frequencies = self.frequencies();
+        self.columns()
+            .zip(self.spectral_centroid())
+            .map(|(column, centroid)| {
+                let total: f64 = column.iter().sum();
+                if total <= 0.0 {
+                    return 0.0;
+                }
+                let variance: f64 = column
+                    .iter()
+                    .zip(frequencies.iter())
+                    .map(|(v, f)| v / total * (f - centroid) * (f - centroid))
+                    .sum();
+                variance.sqrt()
+            })
+            .collect()
+    }
+
+    /// The roll-off frequency (in Hz) of each frame: the frequency of the lowest bin below which `roll_percent` (in [0,1]) of the magnitude of the frame lies.
+    ///
+    /// # Panics
+    /// panics if `roll_percent` is not in [0,1]
+    pub fn spectral_rolloff(&self, roll_percent: f64) -> Vec<f64> {
+        assert!((0.0..=1.0).contains(&roll_percent));
+
+        let frequencies = self.frequencies();
+        self.columns()
+            .map(|column| {
+                let threshold = roll_percent * column.iter().sum::<f64>();
+                let mut cumulative = 0.0;
+                for (v, f) in column.iter().zip(frequencies.iter()) {
+                    cumulative += v;
+                    if cumulative >= threshold {
+                        return *f;
+                    }
+                }
+                frequencies.last().cloned().unwrap_or(0.0)
+            })
+            .collect()
+    }
+
+    /// The flatness (in [0,1]) of each frame: the ratio of the geometric mean of the power spectrum to its arithmetic mean. White noise has a flatness near 1, and a pure tone a flatness near 0.
+    pub fn spectral_flatness(&self) -> Vec<f64> {
+        self.columns()
+            .map(|column| {
+                let n = column.len() as f64;
+                let power: Vec<f64> = column.iter().map(|v| (v * v).max(POWER_AMIN)).collect();
+                let geometric = (power.iter().map(|p| p.ln()).sum::<f64>() / n).exp();
+                let arithmetic = power.iter().sum::<f64>() / n;
+                geometric / arithmetic
+            })
+            .collect()
+    }
+
+    /// The contrast (in dB) between the peaks and valleys of each octave band of each frame, as a spectrogram with `n_bands + 1` rows.
+    ///
+    /// The first band covers every frequency below `fmin`, and the last every frequency above `fmin * 2^(n_bands - 1)`. The peak (and valley) of each band is the mean of the largest (and smallest) `quantile` of its bins.
+    ///
+    /// Bands that contain no bins (e.g. bands above the highest frequency of a constant-Q spectrogram, or of audio at a low sample rate) have a contrast of NaN.
+    ///
+    /// # Panics
+    /// panics if `fmin` is not positive, or `quantile` is not in (0,1)
+    pub fn spectral_contrast(&self, contrast: &Contrast) -> Spectrogram {
+        assert!(contrast.fmin > 0.0, "fmin must be positive");
+        assert!(contrast.quantile > 0.0 && contrast.quantile < 1.0);
+
+        let frequencies = self.frequencies();
+        let bands = contrast_bands(&frequencies[..], contrast);
+
+        let mut data = Vec::with_capacity(self.width as usize * bands.len());
+        for column in self.columns() {
+            for (k, band) in bands.iter().enumerate() {
+                if band.is_empty() {
+                    data.push(f64::NAN);
+                    continue;
+                }
+                let count = ((contrast.quantile * band.len() as f64).round() as usize).max(1);
+
+                // As in librosa, the bin shared with the band above only counts towards the band above
+                let shared = match bands.get(k + 1) {
+                    Some(above) => !above.is_empty() && above.start < band.end,
+                    None => false,
+                };
+                let end = if shared && band.len() > 1 {
+                    band.end - 1
+                } else {
+                    band.end
+                };
+                let mut values = column[band.start..end].to_vec();
+                values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+                let count = count.min(values.len());
+                let mut extremes = [
+                    values[values.len() - count..].iter().sum::<f64>() / count as f64,
+                    values[..count].iter().sum::<f64>() / count as f64,
+                ];
+                db::power_to_db(&mut extremes[..], &Reference::Value(1.0), POWER_AMIN, None);
+                data.push(extremes[0] - extremes[1]);
+            }
+        }
+
+        let mut parameters = self.parameters_or_default();
+        parameters.scaling = "spectral_contrast".to_string();
+        parameters.mel_scale = String::new();
+
+        Spectrogram {
+            width: self.width,
+            height: bands.len() as u32,
+            data,
+            parameters: Some(parameters),
+            ..self.without_data()
+        }
+    }
+
+    /// The frequency (in Hz) of every bin.
+    fn frequencies(&self) -> Vec<f64> {
+        (0..self.height as usize)
+            .map(|bin| self.bin_to_hz(bin))
+            .collect()
+    }
+}
+
+/// The ranges of bins in each band of `spectral_contrast`, as in librosa: each band overlaps the band below by one bin, and the last band extends to the top of the spectrum. Bands that contain no bins of their own are empty.
+fn contrast_bands(frequencies: &[f64], contrast: &Contrast) -> Vec<std::ops::Range<usize>> {
+    let mut edges = vec![0.0];
+    edges.extend((0..=contrast.n_bands).map(|k| contrast.fmin * 2f64.powi(k as i32)));
+
+    edges
+        .windows(2)
+        .enumerate()
+        .map(|(k, edge)| {
+            let high = if k == contrast.n_bands {
+                f64::INFINITY
+            } else {
+                edge[1]
+            };
+            let start = frequencies.iter().position(|f| *f >= edge[0]);
+            let end = frequencies
+                .iter()
+                .rposition(|f| *f <= high)
+                .map(|end| end + 1);
+            match (start, end) {
+                (Some(start), Some(end)) if start < end && k > 0 => start.saturating_sub(1)..end,
+                (Some(start), Some(end)) if start < end => start..end,
+                _ => 0..0,
+            }
+        })
+        .collect()
+}
@@ -103,7 +103,7 @@ where
     }
 
     // Hardcode all this into a single function for now.
-    pub fn stft(&self, data: &Vec<T>) -> Vec<T> {
+    pub fn stft(&self, data: &[T]) -> Vec<T> {
         // for(
         // usize window_start_ix = 0;
         // window_start_ix < data.len() - self.window_size;
@@ -120,7 +120,7 @@ where
         result_vec
     }
 
-    pub fn iter_stft(&self, data: &Vec<T>) -> Vec<T> {
+    pub fn iter_stft(&self, data: &[T]) -> Vec<T> {
         data[..]
             .windows(self.window_size)
             .step_by(self.step_size)
@@ -129,7 +129,7 @@ where
             .collect()
     }
 
-    pub fn par_iter_stft(&self, data: &Vec<T>) -> Vec<T> {
+    pub fn par_iter_stft(&self, data: &[T]) -> Vec<T> {
         data[..]
             .par_windows(self.window_size)
             .step_by(self.step_size)
@@ -137,4 +137,14 @@ where
             .flatten()
             .collect()
     }
+
+    /// computes the complete complex column (all `window_size` bins) of every frame,
+    /// e.g. for modifying and then inverting with `inverse::ISTFT`
+    pub fn par_iter_complex_stft(&self, data: &[T]) -> Vec<Vec<Complex<T>>> {
+        data[..]
+            .par_windows(self.window_size)
+            .step_by(self.step_size)
+            .map(|window| self.compute_complex_column(window))
+            .collect()
+    }
 }
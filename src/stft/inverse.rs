@@ -0,0 +1,88 @@
+/*!
+
+**computes the inverse of the [short-time fourier transform](https://en.wikipedia.org/wiki/Short-time_Fourier_transform)
+computed by `inplace::STFT`, by weighted overlap-add**
+
+each column is inverse transformed, multiplied by the window again, and added to the output at its frame's offset.
+the output is then divided by the sum of the squared windows at each sample, so that an unmodified STFT is inverted exactly
+(apart from any samples after the last frame, which are zero).
+
+*/
+use super::*;
+
+pub struct ISTFT<T>
+where
+    T: FFTnum + FromF64 + num::Float,
+{
+    pub window_size: usize,
+    pub step_size: usize,
+    pub ifft: Arc<dyn FFT<T>>,
+    pub window: Vec<T>,
+}
+
+impl<T> ISTFT<T>
+where
+    T: FFTnum + FromF64 + num::Float,
+{
+    /// takes the same arguments as the `inplace::STFT` being inverted
+    pub fn new(window_type: WindowType, window_size: usize, step_size: usize) -> Self {
+        assert!(step_size > 0 && step_size <= window_size);
+        let window = window_type
+            .as_window_vec(window_size)
+            .unwrap_or_else(|| vec![T::one(); window_size]);
+        let inverse = true;
+        let mut planner = FFTplanner::new(inverse);
+        ISTFT {
+            window_size,
+            step_size,
+            ifft: planner.plan_fft(window_size),
+            window,
+        }
+    }
+
+    /// the real, windowed signal of a single complete (`window_size` bins) complex column
+    pub fn compute_column(&self, column: &[Complex<T>]) -> Vec<T> {
+        assert_eq!(column.len(), self.window_size);
+
+        let mut complex_input = column.to_vec();
+        let mut complex_output: Vec<Complex<T>> = std::iter::repeat(Complex::<T>::zero())
+            .take(self.window_size)
+            .collect();
+        self.ifft.process(&mut complex_input, &mut complex_output);
+
+        // rustfft doesn't normalise the inverse transform
+        let scale: T = FromF64::from_f64(self.window_size as f64);
+        complex_output
+            .iter()
+            .zip(self.window.iter())
+            .map(|(elem, w)| elem.re / scale * *w)
+            .collect()
+    }
+
+    /// inverts a sequence of complete complex columns into `length` samples
+    pub fn istft(&self, columns: &[Vec<Complex<T>>], length: usize) -> Vec<T> {
+        let covered = match columns.len() {
+            0 => 0,
+            n => (n - 1) * self.step_size + self.window_size,
+        };
+        let mut output = vec![T::zero(); covered.max(length)];
+        let mut weights = vec![T::zero(); covered.max(length)];
+
+        for (i, column) in columns.iter().enumerate() {
+            let start = i * self.step_size;
+            let frame = self.compute_column(&column[..]);
+            for (j, (sample, w)) in frame.iter().zip(self.window.iter()).enumerate() {
+                output[start + j] = output[start + j] + *sample;
+                weights[start + j] = weights[start + j] + *w * *w;
+            }
+        }
+
+        for (sample, weight) in output.iter_mut().zip(weights.iter()) {
+            if *weight > T::min_positive_value() {
+                *sample = *sample / *weight;
+            }
+        }
+        output.truncate(length);
+        output
+    }
+}
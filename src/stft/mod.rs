@@ -1,4 +1,5 @@
 pub mod inplace;
+pub mod inverse;
 pub mod streaming;
 
 use std::str::FromStr;
@@ -18,6 +19,7 @@ use rustfft::{FFTnum, FFTplanner, FFT};
 
 /// the type of apodization window to use
 #[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum WindowType {
     Hanning,
     Hamming,
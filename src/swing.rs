@@ -0,0 +1,244 @@
+/*!
+ * Swing ratio estimation from a beat grid.
+ *
+ * In swung eighth notes, the first eighth note of each beat is longer than the second: the swing ratio is the ratio of their lengths, so straight eighths have a ratio of 1, triplet swing a ratio of 2, and a hard shuffle a ratio of 3. The second eighth note of a beat with a swing ratio of `r` starts `r / (1 + r)` of the way through the beat.
+ *
+ * Given an onset strength envelope (see the `onset` module) and the frames of the beats, the onset strength at each position within a beat is averaged over many beats, and the strongest position between the beats gives the swing ratio. The ride cymbal and hi-hat carry the swing, so the envelope of the percussive component (see the `hpss` module) usually gives the most reliable estimates:
+ *
+ * ```ignore
+ * let (_, percussive) = builder.build_hpss(&Hpss::default(), &samples);
+ * let envelope = percussive.onset_strength(&Onset::default());
+ * let analysis = Swing::default().analyse(&envelope, &beats);
+ * println!("{}", analysis.track.unwrap().feel());
+ * ```
+ */
+use std::fmt;
+
+/// The parameters of swing ratio estimation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Swing {
+    /// The range of swing ratios considered.
+    pub min_ratio: f64,
+    pub max_ratio: f64,
+    /// The spacing (as a fraction of a beat) of the positions at which onset strength is measured.
+    pub resolution: f64,
+    /// The number of beats in each section of the swing curve (16 is four bars of 4/4).
+    pub section_beats: usize,
+}
+
+impl Default for Swing {
+    fn default() -> Self {
+        Swing {
+            min_ratio: 1.0,
+            max_ratio: 4.0,
+            resolution: 0.01,
+            section_beats: 16,
+        }
+    }
+}
+
+/// An estimated swing ratio.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SwingEstimate {
+    /// The ratio of the length of the first eighth note of each beat to that of the second.
+    pub ratio: f64,
+    /// How much the onset strength at the second eighth note stands out from that at the other positions considered, in [0,1].
+    pub confidence: f64,
+}
+
+/// How a swing ratio feels to a dancer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Feel {
+    /// Ratios below 1.25.
+    Straight,
+    /// Ratios from 1.25 to 1.75.
+    LightSwing,
+    /// Ratios from 1.75 to 2.5, around triplet swing.
+    Swing,
+    /// Ratios of 2.5 and above.
+    HeavyShuffle,
+}
+
+impl SwingEstimate {
+    /// Classifies the swing ratio (see `Feel`).
+    pub fn feel(&self) -> Feel {
+        match self.ratio {
+            r if r < 1.25 => Feel::Straight,
+            r if r < 1.75 => Feel::LightSwing,
+            r if r < 2.5 => Feel::Swing,
+            _ => Feel::HeavyShuffle,
+        }
+    }
+}
+
+impl fmt::Display for Feel {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Feel::Straight => write!(formatter, "straight"),
+            Feel::LightSwing => write!(formatter, "light swing"),
+            Feel::Swing => write!(formatter, "swing"),
+            Feel::HeavyShuffle => write!(formatter, "heavy shuffle"),
+        }
+    }
+}
+
+/// The swing of a run of consecutive beats.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SwingSection {
+    /// The frames of the first beat of the section, and of the beat after its last.
+    pub start: usize,
+    pub end: usize,
+    /// The swing of the section, or `None` if it has no onsets.
+    pub swing: Option<SwingEstimate>,
+}
+
+/// The swing of a whole track, and of each of its sections.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SwingAnalysis {
+    pub track: Option<SwingEstimate>,
+    pub sections: Vec<SwingSection>,
+}
+
+impl Swing {
+    /// Estimates the swing ratio and the per-section swing curve of an onset envelope, given the frames of its beats (in increasing order).
+    pub fn analyse(&self, onset_envelope: &[f64], beats: &[usize]) -> SwingAnalysis {
+        SwingAnalysis {
+            track: self.estimate(onset_envelope, beats),
+            sections: self.sections(onset_envelope, beats),
+        }
+    }
+
+    /// Estimates the swing ratio over every beat, or `None` if there are fewer than two beats, or no onsets between them.
+    ///
+    /// # Panics
+    /// panics unless `1 <= min_ratio < max_ratio`, and `resolution` is positive
+    pub fn estimate(&self, onset_envelope: &[f64], beats: &[usize]) -> Option<SwingEstimate> {
+        assert!(1.0 <= self.min_ratio && self.min_ratio < self.max_ratio);
+        assert!(self.resolution > 0.0);
+
+        let positions = self.positions();
+        let mut profile = vec![0.0; positions.len()];
+        let mut count = 0;
+        for beat in beats.windows(2) {
+            if let Some(beat_profile) = beat_profile(onset_envelope, beat[0], beat[1], &positions) {
+                profile
+                    .iter_mut()
+                    .zip(beat_profile.iter())
+                    .for_each(|(p, b)| *p += b);
+                count += 1;
+            }
+        }
+        if count == 0 {
+            return None;
+        }
+
+        let (peak, strength) = peak(&profile[..])?;
+        let position = positions[0] + peak * self.resolution;
+        let ratio = position / (1.0 - position);
+
+        let mean = profile.iter().sum::<f64>() / profile.len() as f64;
+        Some(SwingEstimate {
+            ratio: ratio.max(self.min_ratio).min(self.max_ratio),
+            confidence: (strength - mean) / strength,
+        })
+    }
+
+    /// Estimates the swing ratio of each section of `section_beats` beats. The last section may be shorter.
+    ///
+    /// # Panics
+    /// panics if `section_beats` is zero
+    pub fn sections(&self, onset_envelope: &[f64], beats: &[usize]) -> Vec<SwingSection> {
+        assert!(self.section_beats > 0);
+        if beats.len() < 2 {
+            return Vec::new();
+        }
+
+        // Each section includes the beat after its last, so that its last beat has an end
+        (0..beats.len() - 1)
+            .step_by(self.section_beats)
+            .map(|first| {
+                let last = (first + self.section_beats).min(beats.len() - 1);
+                let section = &beats[first..=last];
+                SwingSection {
+                    start: section[0],
+                    end: section[section.len() - 1],
+                    swing: self.estimate(onset_envelope, section),
+                }
+            })
+            .collect()
+    }
+
+    /// The positions (as fractions of a beat) at which onset strength is measured.
+    fn positions(&self) -> Vec<f64> {
+        let (low, high) = (
+            self.min_ratio / (1.0 + self.min_ratio),
+            self.max_ratio / (1.0 + self.max_ratio),
+        );
+        let count = ((high - low) / self.resolution).floor() as usize + 1;
+        (0..count)
+            .map(|i| low + i as f64 * self.resolution)
+            .collect()
+    }
+}
+
+/// The onset strength at each position within a beat, relative to the strongest onset in the beat. `None` if the beat has no onsets (or lies outside the envelope).
+fn beat_profile(
+    onset_envelope: &[f64],
+    start: usize,
+    end: usize,
+    positions: &[f64],
+) -> Option<Vec<f64>> {
+    if end <= start || end >= onset_envelope.len() {
+        return None;
+    }
+    let max = onset_envelope[start..=end]
+        .iter()
+        .fold(0.0f64, |m, v| m.max(*v));
+    if max <= 0.0 {
+        return None;
+    }
+
+    let length = (end - start) as f64;
+    Some(
+        positions
+            .iter()
+            .map(|p| interpolate(onset_envelope, start as f64 + p * length) / max)
+            .collect(),
+    )
+}
+
+/// The linearly interpolated value of an envelope at a fractional frame.
+fn interpolate(envelope: &[f64], frame: f64) -> f64 {
+    let below = frame.floor() as usize;
+    let fraction = frame - below as f64;
+    let next = envelope.get(below + 1).cloned().unwrap_or(0.0);
+    envelope[below] * (1.0 - fraction) + next * fraction
+}
+
+/// The (parabolically interpolated) index and value of the largest value of a profile, or `None` if it is all zeros.
+fn peak(profile: &[f64]) -> Option<(f64, f64)> {
+    let (index, value) =
+        profile.iter().cloned().enumerate().fold(
+            (0, 0.0),
+            |(i, m), (j, v)| if v > m { (j, v) } else { (i, m) },
+        );
+    if value <= 0.0 {
+        return None;
+    }
+    if index == 0 || index == profile.len() - 1 {
+        return Some((index as f64, value));
+    }
+
+    let (a, b, c) = (profile[index - 1], value, profile[index + 1]);
+    let denominator = a - 2.0 * b + c;
+    if denominator == 0.0 {
+        return Some((index as f64, value));
+    }
+    let shift = 0.5 * (a - c) / denominator;
+    Some((index as f64 + shift, b - 0.25 * (a - c) * shift))
+}
@@ -0,0 +1,98 @@
+/*!
+ * Beat-synchronous aggregation of frame-aligned features, equivalent to `librosa.util.sync`.
+ *
+ * Any spectrogram whose columns are aligned with the frames of the beat grid (an STFT or constant-Q spectrogram, a chromagram, MFCCs, ...) can be reduced to one column per beat, by aggregating the columns between each pair of beats. Beat-synchronous features don't depend on the tempo of the recording, so two recordings of the same tune at different tempi can be compared column by column:
+ *
+ * ```ignore
+ * let chroma = spectrogram.chroma(None).beat_sync(&beats, Aggregation::Median);
+ * ```
+ *
+ * As in librosa, the columns before the first beat and after the last beat are aggregated too, so a spectrogram synchronised to `n` beats (none of which are at frame 0) has `n + 1` columns. `beat_sync_frames` gives the first frame of each column.
+ */
+use super::Spectrogram;
+
+use std::fmt;
+
+/// How the columns between beats are combined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Aggregation {
+    Mean,
+    Median,
+    Max,
+}
+
+impl fmt::Display for Aggregation {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Aggregation::Mean => write!(formatter, "mean"),
+            Aggregation::Median => write!(formatter, "median"),
+            Aggregation::Max => write!(formatter, "max"),
+        }
+    }
+}
+
+impl Aggregation {
+    /// Aggregates some (non-empty) values.
+    pub fn apply(&self, values: &mut [f64]) -> f64 {
+        match self {
+            Aggregation::Mean => values.iter().sum::<f64>() / values.len() as f64,
+            Aggregation::Median => {
+                values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+                let middle = values.len() / 2;
+                if values.len().is_multiple_of(2) {
+                    (values[middle - 1] + values[middle]) / 2.0
+                } else {
+                    values[middle]
+                }
+            }
+            Aggregation::Max => values.iter().fold(f64::MIN, |m, v| m.max(*v)),
+        }
+    }
+}
+
+impl Spectrogram {
+    /// Aggregates the columns of the spectrogram between each pair of beats (and before the first, and after the last), given the frames of the beats.
+    ///
+    /// Beats are sorted, and duplicate beats, and beats outside the spectrogram, are ignored. The aggregation is recorded in the result's `parameters`.
+    pub fn beat_sync(&self, beats: &[usize], aggregation: Aggregation) -> Spectrogram {
+        let width = self.width as usize;
+        let height = self.height as usize;
+        let starts = self.beat_sync_frames(beats);
+
+        let mut data = Vec::with_capacity(starts.len() * height);
+        let mut values = Vec::new();
+        for (i, start) in starts.iter().enumerate() {
+            let end = starts.get(i + 1).cloned().unwrap_or(width);
+            for bin in 0..height {
+                values.clear();
+                values.extend((*start..end).map(|t| self.data[t * height + bin]));
+                data.push(aggregation.apply(&mut values[..]));
+            }
+        }
+
+        let mut parameters = self.parameters_or_default();
+        parameters.aggregation = aggregation.to_string();
+        Spectrogram {
+            width: starts.len() as u32,
+            height: self.height,
+            data,
+            parameters: Some(parameters),
+            ..self.without_data()
+        }
+    }
+
+    /// The first frame of each column of `beat_sync(beats, ...)`.
+    pub fn beat_sync_frames(&self, beats: &[usize]) -> Vec<usize> {
+        let width = self.width as usize;
+        if width == 0 {
+            return Vec::new();
+        }
+
+        let mut starts = vec![0];
+        starts.extend(beats.iter().cloned().filter(|b| *b < width));
+        starts.sort();
+        starts.dedup();
+        starts
+    }
+}
@@ -0,0 +1,223 @@
+/*!
+ * Autocorrelation and Fourier tempograms, equivalent to `librosa.feature.tempogram` and `librosa.feature.fourier_tempogram`.
+ *
+ * A tempogram describes the local periodicity of an onset strength envelope (see the `onset` module): each column is computed from a window of the envelope centred on a frame, and each row is a tempo. Tempograms are returned as spectrograms with one column per frame of the onset envelope, so they line up with the spectrogram the envelope was computed from, and can be sliced, saved and rendered like any other spectrogram:
+ *
+ * ```ignore
+ * let envelope = spectrogram.onset_strength(&Onset::default());
+ * let tempogram = Tempogram::default().autocorrelation(&envelope, &spectrogram);
+ * tempogram.as_image_col().save("tempogram.png")?;
+ * // Fourier tempograms aren't in [0,1], so normalise them first
+ * let fourier = Tempogram::default().fourier(&envelope, &spectrogram);
+ * fourier.normalised(&Normalisation::MinMax).as_image_col().save("fourier.png")?;
+ * ```
+ *
+ * The rows of an autocorrelation tempogram are lags (in frames), so row `k` is a tempo of `60 * sample_rate / (step_size * k)` BPM, while the rows of a Fourier tempogram are linearly spaced tempo frequencies. `Spectrogram::bin_to_bpm` converts rows of either to BPM.
+ */
+use super::{Parameters, Spectrogram};
+
+use num::complex::Complex;
+use num::traits::Zero;
+use rayon::prelude::*;
+use rustfft::FFTplanner;
+
+/// The `scaling` recorded in the parameters of autocorrelation tempograms.
+pub const AUTOCORRELATION: &str = "autocorrelation_tempogram";
+
+/// The `scaling` recorded in the parameters of Fourier tempograms.
+pub const FOURIER: &str = "fourier_tempogram";
+
+/// The parameters of a tempogram. The default parameters are those of `librosa.feature.tempogram`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Tempogram {
+    /// The length (in frames) of the window of the onset envelope used for each column.
+    pub window_length: usize,
+}
+
+impl Default for Tempogram {
+    fn default() -> Self {
+        Tempogram { window_length: 384 }
+    }
+}
+
+impl Tempogram {
+    /// Computes the autocorrelation tempogram of an onset envelope, as `librosa.feature.tempogram`.
+    ///
+    /// `spectrogram` is the spectrogram the envelope was computed from, whose parameters, source and timestamp are kept by the tempogram. The tempogram has `window_length` rows (lags), and each column is normalised so that its largest value is 1.
+    ///
+    /// # Panics
+    /// panics if `window_length` is zero
+    pub fn autocorrelation(
+        &self,
+        onset_envelope: &[f64],
+        spectrogram: &Spectrogram,
+    ) -> Spectrogram {
+        assert!(self.window_length > 0);
+        let length = self.window_length;
+
+        // Pad the envelope with linear ramps down to zero, so that the windows are centred on each frame
+        let pad = length / 2;
+        let (first, last) = match (onset_envelope.first(), onset_envelope.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => (0.0, 0.0),
+        };
+        let padded: Vec<f64> = (0..pad)
+            .map(|i| first * i as f64 / pad as f64)
+            .chain(onset_envelope.iter().cloned())
+            .chain((0..pad).map(|i| last * (pad - 1 - i) as f64 / pad as f64))
+            .collect();
+
+        // Autocorrelate each window through the FFT, padded to avoid circular wrap-around
+        let fft_size = 2 * length;
+        let fft = FFTplanner::new(false).plan_fft(fft_size);
+        let ifft = FFTplanner::new(true).plan_fft(fft_size);
+        let window = hann(length);
+
+        let data = (0..onset_envelope.len())
+            .into_par_iter()
+            .flat_map(|t| {
+                let mut input: Vec<Complex<f64>> = vec![Complex::zero(); fft_size];
+                for (i, w) in window.iter().enumerate() {
+                    input[i] = Complex::new(padded.get(t + i).cloned().unwrap_or(0.0) * w, 0.0);
+                }
+                let mut spectrum = vec![Complex::zero(); fft_size];
+                fft.process(&mut input, &mut spectrum);
+
+                let mut power: Vec<Complex<f64>> = spectrum
+                    .iter()
+                    .map(|c| Complex::new(c.norm_sqr(), 0.0))
+                    .collect();
+                let mut autocorrelation = vec![Complex::zero(); fft_size];
+                ifft.process(&mut power, &mut autocorrelation);
+
+                let column: Vec<f64> = autocorrelation[..length]
+                    .iter()
+                    .map(|c| c.re / fft_size as f64)
+                    .collect();
+                max_normalise(column)
+            })
+            .collect();
+
+        self.tempogram(
+            data,
+            length,
+            onset_envelope.len(),
+            spectrogram,
+            AUTOCORRELATION,
+        )
+    }
+
+    /// Computes the magnitude of the Fourier tempogram of an onset envelope, as `np.abs(librosa.feature.fourier_tempogram(...))`.
+    ///
+    /// `spectrogram` is the spectrogram the envelope was computed from, as for `autocorrelation`. The tempogram has `window_length / 2 + 1` rows, and its `fmin` and `fmax` are tempo frequencies in Hz (i.e. BPM / 60).
+    ///
+    /// # Panics
+    /// panics if `window_length` is zero
+    pub fn fourier(&self, onset_envelope: &[f64], spectrogram: &Spectrogram) -> Spectrogram {
+        assert!(self.window_length > 0);
+        let length = self.window_length;
+        let height = length / 2 + 1;
+
+        // Pad the envelope with zeros, so that the windows are centred on each frame
+        let pad = length / 2;
+        let padded: Vec<f64> = std::iter::repeat_n(0.0, pad)
+            .chain(onset_envelope.iter().cloned())
+            .chain(std::iter::repeat_n(0.0, pad))
+            .collect();
+
+        let fft = FFTplanner::new(false).plan_fft(length);
+        let window = hann(length);
+
+        let data = (0..onset_envelope.len())
+            .into_par_iter()
+            .flat_map(|t| {
+                let mut input: Vec<Complex<f64>> = window
+                    .iter()
+                    .enumerate()
+                    .map(|(i, w)| Complex::new(padded.get(t + i).cloned().unwrap_or(0.0) * w, 0.0))
+                    .collect();
+                let mut spectrum = vec![Complex::zero(); length];
+                fft.process(&mut input, &mut spectrum);
+
+                spectrum[..height]
+                    .iter()
+                    .map(|c| c.norm())
+                    .collect::<Vec<f64>>()
+            })
+            .collect();
+
+        let mut tempogram =
+            self.tempogram(data, height, onset_envelope.len(), spectrogram, FOURIER);
+        let fmax = tempogram.bin_to_hz(height);
+        if let Some(ref mut p) = tempogram.parameters {
+            p.fmax = fmax;
+        }
+        tempogram
+    }
+
+    fn tempogram(
+        &self,
+        data: Vec<f64>,
+        height: usize,
+        width: usize,
+        spectrogram: &Spectrogram,
+        scaling: &str,
+    ) -> Spectrogram {
+        // The window spans `window_length` frames of audio, which makes `bin_to_hz` give the tempo frequencies of Fourier tempograms
+        let parameters = spectrogram.parameters_or_default();
+        let parameters = Parameters {
+            fft_size: self.window_length as u32 * parameters.step_size,
+            fmin: 0.0,
+            fmax: 0.0,
+            bins_per_octave: 0,
+            filter_scale: 0.0,
+            scaling: scaling.to_string(),
+            normalisation: String::new(),
+            mel_scale: String::new(),
+            ..parameters.clone()
+        };
+
+        Spectrogram {
+            width: width as u32,
+            height: height as u32,
+            data,
+            parameters: Some(parameters),
+            ..spectrogram.without_data()
+        }
+    }
+}
+
+impl Spectrogram {
+    /// The tempo (in BPM) of a row of a tempogram, as `librosa.tempo_frequencies` (for autocorrelation tempograms) or `librosa.fourier_tempo_frequencies` (for Fourier tempograms).
+    ///
+    /// Row 0 of an autocorrelation tempogram (a lag of zero) is an infinite tempo. For spectrograms other than tempograms, this is the frequency of the bin in cycles per minute.
+    pub fn bin_to_bpm(&self, bin: usize) -> f64 {
+        let parameters = self.parameters_or_default();
+        if parameters.scaling == AUTOCORRELATION {
+            return 60.0 * frame_rate(&parameters) / bin as f64;
+        }
+        60.0 * self.bin_to_hz(bin)
+    }
+}
+
+/// The number of frames per second of a spectrogram.
+fn frame_rate(parameters: &Parameters) -> f64 {
+    parameters.sample_rate as f64 / parameters.step_size as f64
+}
+
+/// A periodic Hann window, as `scipy.signal.get_window('hann', n)`.
+fn hann(n: usize) -> Vec<f64> {
+    (0..n)
+        .map(|i| 0.5 - 0.5 * (2.0 * std::f64::consts::PI * i as f64 / n as f64).cos())
+        .collect()
+}
+
+/// Divides a column by its largest absolute value, as `librosa.util.normalize`. Columns of (nearly) all zeros are left as they are.
+fn max_normalise(mut column: Vec<f64>) -> Vec<f64> {
+    let max = column.iter().fold(0.0f64, |m, v| m.max(v.abs()));
+    if max > f64::MIN_POSITIVE {
+        column.iter_mut().for_each(|v| *v /= max);
+    }
+    column
+}
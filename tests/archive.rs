@@ -0,0 +1,88 @@
+use prost::Message;
+use tizol::archive::{ArchiveReader, ArchiveWriter};
+use tizol::{ArchiveEntry, ArchiveIndex, Encoding, Spectrogram};
+
+use std::io::Cursor;
+
+fn spectrogram(seed: f64) -> Spectrogram {
+    Spectrogram {
+        width: 3,
+        height: 2,
+        data: (0..6).map(|i| (seed + i as f64) / 10.0).collect(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn archive_random_access() {
+    let mut buf = Cursor::new(Vec::new());
+    {
+        let mut writer = ArchiveWriter::new(&mut buf).unwrap();
+        writer.append("one", &spectrogram(1.0)).unwrap();
+        writer
+            .append("two", &spectrogram(2.0).with_encoding(Encoding::Float).unwrap())
+            .unwrap();
+        writer.append("three", &spectrogram(3.0)).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let mut reader = ArchiveReader::new(buf).unwrap();
+    assert_eq!(reader.len(), 3);
+    assert!(reader.contains("two"));
+
+    assert_eq!(reader.get("three").unwrap().unwrap(), spectrogram(3.0));
+    assert_eq!(reader.get("one").unwrap().unwrap(), spectrogram(1.0));
+    assert!(reader.get("four").unwrap().is_none());
+
+    // Compact records are expanded as they are read
+    let two = reader.get("two").unwrap().unwrap();
+    assert_eq!(two.encoding(), Encoding::Double);
+    assert_eq!(two.data.len(), 6);
+}
+
+#[test]
+fn archive_append_to_existing() {
+    let path = std::env::temp_dir().join(format!("tizol-archive-{}.tza", std::process::id()));
+
+    let mut writer = ArchiveWriter::create(&path).unwrap();
+    writer.append("one", &spectrogram(1.0)).unwrap();
+    writer.finish().unwrap();
+
+    let mut writer = ArchiveWriter::append_to(&path).unwrap();
+    writer.append("two", &spectrogram(2.0)).unwrap();
+    writer.append("one", &spectrogram(5.0)).unwrap();
+    drop(writer);
+
+    let mut reader = ArchiveReader::open(&path).unwrap();
+    let mut keys: Vec<&str> = reader.keys().collect();
+    keys.sort();
+    assert_eq!(keys, vec!["one", "two"]);
+    assert_eq!(reader.get("one").unwrap().unwrap(), spectrogram(5.0));
+    assert_eq!(reader.get("two").unwrap().unwrap(), spectrogram(2.0));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn unfinished_archive_is_rejected() {
+    assert!(ArchiveReader::new(Cursor::new(b"TIZOLARC".to_vec())).is_err());
+    assert!(ArchiveReader::new(Cursor::new(b"not an archive at all".to_vec())).is_err());
+}
+
+#[test]
+fn out_of_range_index_entry_is_rejected() {
+    // An index entry claiming a record far larger than the archive
+    let index = ArchiveIndex {
+        entries: vec![ArchiveEntry {
+            key: "one".to_string(),
+            offset: 8,
+            length: 1 << 40,
+        }],
+    };
+    let mut bytes = b"TIZOLARC".to_vec();
+    index.encode(&mut bytes).unwrap();
+    bytes.extend_from_slice(&8u64.to_le_bytes());
+    bytes.extend_from_slice(b"TZOLINDX");
+
+    assert!(ArchiveReader::new(Cursor::new(bytes)).is_err());
+}
@@ -0,0 +1,49 @@
+#![cfg(feature = "ndarray")]
+
+use ndarray::{arr2, Axis};
+use tizol::Spectrogram;
+
+fn spectrogram() -> Spectrogram {
+    // Three frequency bins, and two frames
+    Spectrogram {
+        width: 2,
+        height: 3,
+        data: vec![0.0, 0.1, 0.2, 1.0, 1.1, 1.2],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn view_has_frequency_time_axes() {
+    let sp = spectrogram();
+    let view = sp.view();
+
+    assert_eq!(view.dim(), (3, 2));
+    assert_eq!(view[[2, 0]], 0.2);
+    assert_eq!(view[[0, 1]], 1.0);
+    assert_eq!(view.index_axis(Axis(1), 1).to_vec(), vec![1.0, 1.1, 1.2]);
+}
+
+#[test]
+fn view_mut_writes_through() {
+    let mut sp = spectrogram();
+    sp.view_mut()[[1, 1]] = 5.0;
+    sp.view_mut().index_axis_mut(Axis(1), 0).fill(-1.0);
+
+    assert_eq!(sp.data, vec![-1.0, -1.0, -1.0, 1.0, 5.0, 1.2]);
+}
+
+#[test]
+fn from_array_roundtrip() {
+    // A standard (row major) array is reordered into columns
+    let array = arr2(&[[0.0, 1.0], [0.1, 1.1], [0.2, 1.2]]);
+    let sp = Spectrogram::from_array(&array);
+
+    assert_eq!((sp.width, sp.height), (2, 3));
+    assert_eq!(sp.data, spectrogram().data);
+    assert_eq!(sp.to_array(), array);
+
+    // As is a transposed (column major) view
+    let transposed = arr2(&[[0.0, 0.1, 0.2], [1.0, 1.1, 1.2]]);
+    assert_eq!(Spectrogram::from_array(&transposed.t()).data, sp.data);
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use common::{magnitudes, tones};
+use tizol::chroma::{Key, Mode};
+use tizol::Spectrogram;
+
+#[test]
+fn tuning_estimation() {
+    // A6, in tune, and a fifth of a semitone sharp
+    let in_tune = magnitudes(&tones(&[1760.0], 1.0));
+    assert!(in_tune.estimate_tuning().abs() < 0.05);
+
+    let sharp = magnitudes(&tones(&[1760.0 * 2f64.powf(0.2 / 12.0)], 1.0));
+    assert!((sharp.estimate_tuning() - 0.2).abs() < 0.05);
+}
+
+#[test]
+fn chroma_of_a_chord() {
+    // A C major triad: C5, E5 and G5
+    let sp = magnitudes(&tones(&[523.25, 659.26, 783.99], 1.0));
+    let chroma = sp.chroma(Some(0.0));
+
+    assert_eq!((chroma.width, chroma.height), (sp.width, 12));
+    let column = chroma.column(chroma.width as usize / 2);
+    for class in &[0, 4, 7] {
+        assert!(column[*class] > 0.5, "{:?}", column);
+    }
+    // Spectral leakage spreads some energy into neighbouring semitones, but no further
+    for class in &[1, 2, 3, 5, 6, 8, 9, 10, 11] {
+        assert!(column[*class] < 0.3, "{:?}", column);
+    }
+    // The rows are the pitch classes of the fourth octave
+    assert!((chroma.bin_to_hz(9) - 440.0).abs() < 1e-6);
+}
+
+#[test]
+fn key_from_profiles() {
+    // A chromagram following the Krumhansl-Kessler profile of C major, transposed to A
+    let c_major = [
+        6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+    ];
+    let chroma = Spectrogram {
+        width: 1,
+        height: 12,
+        data: (0..12).map(|c| c_major[(c + 12 - 9) % 12]).collect(),
+        ..Default::default()
+    };
+
+    let key = Key::estimate(&chroma).unwrap();
+    assert_eq!(key.mode, Mode::Major);
+    assert_eq!(key.to_string(), "A major");
+    assert!((key.confidence - 1.0).abs() < 1e-9);
+
+    // Silence has no key
+    let silent = Spectrogram {
+        width: 1,
+        height: 12,
+        data: vec![0.0; 12],
+        ..Default::default()
+    };
+    assert_eq!(Key::estimate(&silent), None);
+}
+
+#[test]
+fn key_of_a_minor_chord() {
+    // An A minor triad, with a strong tonic: A4, C5, E5 and A5
+    let sp = magnitudes(&tones(&[440.0, 523.25, 659.26, 880.0], 1.0));
+    let key = sp.estimate_key().unwrap();
+
+    assert_eq!(key.tonic_name(), "A");
+    assert_eq!(key.mode, Mode::Minor);
+}
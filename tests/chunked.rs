@@ -0,0 +1,98 @@
+use tizol::chunked::{ChunkReader, ChunkWriter};
+use tizol::stft::inplace::STFT;
+use tizol::stft::WindowType;
+use tizol::{Spectrogram, SpectrogramHeader};
+
+use std::io::Cursor;
+
+#[test]
+fn chunked_roundtrip() {
+    let samples: Vec<f64> = (0..44100).map(|i| (i as f64 * 0.05).sin()).collect();
+    let sp = Spectrogram::from_buffer(&samples);
+
+    let buf = sp.write_chunked(Vec::new(), 16).unwrap();
+    let decoded = Spectrogram::read_chunked(&buf[..]).unwrap();
+
+    assert_eq!(decoded, sp);
+}
+
+#[test]
+fn stream_columns_as_they_are_computed() {
+    let samples: Vec<f64> = (0..8192).map(|i| (i as f64 * 0.3).sin()).collect();
+    let stft = STFT::<f64>::new(WindowType::Hanning, 256, 64);
+
+    let header = SpectrogramHeader {
+        height: stft.output_size() as u32,
+        ..Default::default()
+    };
+    let mut writer = ChunkWriter::new(Vec::new(), header, 10).unwrap();
+
+    let mut expected = Vec::new();
+    for window in samples.windows(256).step_by(64) {
+        let column = stft.compute_magnitude_column(window);
+        writer.write_column(&column[..]).unwrap();
+        expected.extend(column);
+    }
+    let frames = writer.frames();
+    let buf = writer.finish().unwrap();
+
+    let reader = ChunkReader::new(Cursor::new(buf)).unwrap();
+    assert_eq!(reader.header().height, 128);
+
+    let mut data = Vec::new();
+    let mut next_frame = 0;
+    for block in reader {
+        let block = block.unwrap();
+        assert_eq!(block.start_frame, next_frame);
+        assert!(block.width <= 10);
+        next_frame += block.width as u64;
+        data.extend(block.data);
+    }
+
+    assert_eq!(next_frame, frames);
+    assert_eq!(data, expected);
+}
+
+#[test]
+fn stream_without_header_is_rejected() {
+    let sp = Spectrogram {
+        width: 2,
+        height: 2,
+        data: vec![0.0; 4],
+        ..Default::default()
+    };
+    let buf = sp.write_chunked(Vec::new(), 1).unwrap();
+
+    // Skip the header chunk (a single byte length, followed by the chunk)
+    let header_len = buf[0] as usize + 1;
+    assert!(ChunkReader::new(&buf[header_len..]).is_err());
+    assert!(ChunkReader::new(&buf[..]).is_ok());
+}
+
+#[test]
+fn empty_spectrogram_is_rejected() {
+    let err = Spectrogram::default()
+        .write_chunked(Vec::new(), 16)
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn oversized_chunk_is_rejected() {
+    let sp = Spectrogram {
+        width: 2,
+        height: 2,
+        data: vec![0.0; 4],
+        ..Default::default()
+    };
+    let mut buf = sp.write_chunked(Vec::new(), 1).unwrap();
+
+    // Replace the first column block with a length delimiter claiming 16 GiB
+    let header_len = buf[0] as usize + 1;
+    buf.truncate(header_len);
+    buf.extend_from_slice(&[0x80, 0x80, 0x80, 0x80, 0x40]);
+
+    let mut reader = ChunkReader::new(&buf[..]).unwrap();
+    let err = reader.next_block().unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
@@ -0,0 +1,27 @@
+//! Fixtures for the tests of the feature extraction modules (chroma, MFCCs, spectral features, ...), which work on magnitudes rather than normalised dB values.
+
+use tizol::builder::Scaling;
+use tizol::normalisation::Normalisation;
+use tizol::{Spectrogram, SAMPLE_RATE};
+
+/// A sum of unit amplitude sinusoids, `seconds` long
+pub fn tones(frequencies: &[f64], seconds: f64) -> Vec<f64> {
+    let count = (seconds * SAMPLE_RATE as f64) as usize;
+    (0..count)
+        .map(|i| {
+            let t = i as f64 / SAMPLE_RATE as f64;
+            frequencies
+                .iter()
+                .map(|f| (2.0 * std::f64::consts::PI * f * t).sin())
+                .sum::<f64>()
+        })
+        .collect()
+}
+
+/// The STFT magnitudes of some samples, without a dB conversion or normalisation, as librosa's feature functions expect
+pub fn magnitudes(samples: &[f64]) -> Spectrogram {
+    Spectrogram::builder()
+        .scaling(Scaling::Magnitude)
+        .normalisation(Normalisation::None)
+        .build(samples)
+}
@@ -0,0 +1,18 @@
+//! Fixtures shared by the integration tests.
+
+// Each test crate only uses some of the fixtures
+#![allow(dead_code, unused_imports)]
+
+mod features;
+
+pub use self::features::{magnitudes, tones};
+
+use tizol::SAMPLE_RATE;
+
+/// A unit amplitude sinusoid, `seconds` long
+pub fn sine(frequency: f64, seconds: f64) -> Vec<f64> {
+    let count = (seconds * SAMPLE_RATE as f64) as usize;
+    (0..count)
+        .map(|i| (2.0 * std::f64::consts::PI * frequency * i as f64 / SAMPLE_RATE as f64).sin())
+        .collect()
+}
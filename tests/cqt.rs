@@ -0,0 +1,113 @@
+mod common;
+
+use common::sine;
+use tizol::cqt::{Cqt, C1};
+use tizol::stft::WindowType;
+use tizol::{Spectrogram, SAMPLE_RATE};
+
+/// Three octaves from A2, which keeps the kernel (and the tests) small
+fn small_cqt() -> Cqt {
+    Cqt {
+        fmin: 110.0,
+        n_bins: 36,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn frequencies_are_log_spaced() {
+    let cqt = Cqt::default();
+    let frequencies = cqt.frequencies();
+
+    assert_eq!(frequencies.len(), 84);
+    assert!((frequencies[0] - C1).abs() < 1e-9);
+    // A4 is 45 semitones above C1
+    assert!((frequencies[45] - 440.0).abs() < 1e-6);
+    assert!((frequencies[12] / frequencies[0] - 2.0).abs() < 1e-9);
+    assert!((cqt.q() - 16.817).abs() < 1e-3);
+}
+
+#[test]
+fn sinusoid_peaks_at_its_bin() {
+    let samples = sine(440.0, 1.0);
+    let kernel = small_cqt().kernel(WindowType::Hanning, SAMPLE_RATE);
+    assert!(kernel.fft_size() >= kernel.filter_length());
+
+    let column = kernel.compute_column(&samples[..], samples.len() / 2);
+    let (peak, magnitude) =
+        column.iter().enumerate().fold(
+            (0, 0.0),
+            |(pi, pm), (i, m)| if *m > pm { (i, *m) } else { (pi, pm) },
+        );
+
+    assert_eq!(peak, 24);
+    // A unit amplitude sinusoid has a magnitude of (roughly) a half
+    assert!((magnitude - 0.5).abs() < 0.02, "{}", magnitude);
+    // And the neighbouring semitones are much quieter
+    assert!(column[22] < magnitude / 10.0 && column[26] < magnitude / 10.0);
+}
+
+#[test]
+fn builder_computes_cqt_spectrograms() {
+    let samples = sine(440.0, 1.0);
+    let stft = Spectrogram::from_buffer(&samples);
+    let cqt = Spectrogram::builder().cqt(small_cqt()).build(&samples);
+
+    // The same frames as the STFT, but with log-spaced bins
+    assert_eq!((cqt.width, cqt.height), (stft.width, 36));
+    let parameters = cqt.parameters.clone().unwrap();
+    assert_eq!(parameters.bins_per_octave, 12);
+    assert_eq!(parameters.filter_scale, 1.0);
+
+    assert!((cqt.bin_to_hz(24) - 440.0).abs() < 1e-6);
+    assert_eq!(cqt.hz_to_bin(440.0), 24);
+    assert_eq!(cqt.hz_to_bin(0.0), 0);
+
+    // The loudest bin of the middle column is A4
+    let column = cqt.column(cqt.width as usize / 2);
+    let loudest = (0..column.len())
+        .max_by(|a, b| column[*a].partial_cmp(&column[*b]).unwrap())
+        .unwrap();
+    assert_eq!(loudest, 24);
+
+    // Slicing preserves the frequencies of the bins
+    let octave = cqt.slice_hz(220.0, 440.0);
+    assert_eq!(octave.height, 12);
+    assert!((octave.bin_to_hz(0) - 220.0).abs() < 1e-6);
+}
+
+#[test]
+fn cqt_images_mark_octaves() {
+    let samples = sine(440.0, 1.0);
+    let cqt = Spectrogram::builder().cqt(small_cqt()).build(&samples);
+
+    let octaves = cqt.octave_rows();
+    assert_eq!(
+        octaves.iter().map(|(row, _)| *row).collect::<Vec<_>>(),
+        vec![0, 12, 24]
+    );
+    assert!((octaves[2].1 - 440.0).abs() < 1e-6);
+
+    // The bins are the rows of the image, from the bottom up, with the start of each octave (above the first) lightened
+    let plain = cqt.as_image_col();
+    let img = cqt.as_image_cqt();
+    assert_eq!(img.dimensions(), (cqt.width, cqt.height));
+    for y in 0..cqt.height {
+        let row = cqt.height - y - 1;
+        let (a, b) = (plain.get_pixel(0, y), img.get_pixel(0, y));
+        if row == 12 || row == 24 {
+            assert!(b.0.iter().zip(a.0.iter()).all(|(b, a)| b >= a));
+            assert_ne!(a, b);
+        } else {
+            assert_eq!(a, b);
+        }
+    }
+
+    // STFT spectrograms have no octaves
+    let stft = Spectrogram::from_buffer(&samples);
+    assert!(stft.octave_rows().is_empty());
+    assert_eq!(
+        stft.as_image_cqt().into_raw(),
+        stft.as_image_col().into_raw()
+    );
+}
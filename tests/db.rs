@@ -0,0 +1,72 @@
+use tizol::db::{self, Reference, POWER_AMIN};
+use tizol::{Spectrogram, AMIN};
+
+fn assert_close(a: &[f64], b: &[f64]) {
+    assert_eq!(a.len(), b.len());
+    for (x, y) in a.iter().zip(b.iter()) {
+        assert!((x - y).abs() < 1e-9, "{} != {}", x, y);
+    }
+}
+
+#[test]
+fn power_to_db_references() {
+    let mut s = [1.0, 10.0, 100.0, 0.0];
+    db::power_to_db(&mut s[..], &Reference::Value(1.0), POWER_AMIN, None);
+    assert_close(&s[..], &[0.0, 10.0, 20.0, -100.0]);
+
+    let mut s = [1.0, 10.0, 100.0, 0.0];
+    db::power_to_db(&mut s[..], &Reference::Max, POWER_AMIN, Some(80.0));
+    assert_close(&s[..], &[-20.0, -10.0, 0.0, -80.0]);
+
+    let mut s = [1.0, 10.0, 100.0];
+    db::power_to_db(&mut s[..], &Reference::Median, POWER_AMIN, None);
+    assert_close(&s[..], &[-10.0, 0.0, 10.0]);
+
+    let mut s = [1.0, 10.0, 100.0];
+    db::power_to_db(&mut s[..], &Reference::Function(|v| v[0]), POWER_AMIN, None);
+    assert_close(&s[..], &[0.0, 10.0, 20.0]);
+}
+
+#[test]
+fn amplitude_to_db_clipping() {
+    let mut s = [-1.0, 0.1, 1e-9];
+    db::amplitude_to_db(&mut s[..], &Reference::Value(1.0), AMIN, None);
+    assert_close(&s[..], &[0.0, -20.0, -100.0]);
+
+    let mut s = [-1.0, 0.1, 1e-9];
+    db::amplitude_to_db(&mut s[..], &Reference::Max, AMIN, Some(40.0));
+    assert_close(&s[..], &[0.0, -20.0, -40.0]);
+}
+
+#[test]
+fn inverse_conversions() {
+    let original = vec![0.5, 2.0, 4.0];
+
+    let mut s = original.clone();
+    db::power_to_db(&mut s[..], &Reference::Value(2.0), POWER_AMIN, None);
+    db::db_to_power(&mut s[..], 2.0);
+    assert_close(&s[..], &original[..]);
+
+    let mut s = original.clone();
+    db::amplitude_to_db(&mut s[..], &Reference::Value(2.0), AMIN, None);
+    db::db_to_amplitude(&mut s[..], 2.0);
+    assert_close(&s[..], &original[..]);
+}
+
+#[test]
+fn builder_defaults_match_from_buffer() {
+    let samples: Vec<f64> = (0..44100).map(|i| (i as f64 * 0.05).sin()).collect();
+    let default = Spectrogram::from_buffer(&samples);
+    let built = Spectrogram::builder().build(&samples);
+    assert_eq!(built.data, default.data);
+    assert_eq!(built.parameters, default.parameters);
+
+    let absolute = Spectrogram::builder()
+        .reference(Reference::Value(1.0))
+        .top_db(None)
+        .build(&samples);
+    let parameters = absolute.parameters.unwrap();
+    assert_eq!(parameters.db_reference, "1");
+    assert_eq!(parameters.top_db, 0.0);
+    assert_eq!(absolute.width, default.width);
+}
@@ -0,0 +1,189 @@
+use tizol::export::{write_png16, write_tiff_f32, BitDepth, ImageMetadata, Orientation};
+use tizol::Spectrogram;
+
+use tiff::decoder::ifd::Tag;
+
+use std::io::Cursor;
+
+fn ramp() -> Spectrogram {
+    // Four columns of three rows, covering (and slightly exceeding) the [0,1] range.
+    let data = vec![
+        0.0, 0.25, 0.5, //
+        0.75, 1.0, 1.5, //
+        -0.5, 0.1, 0.2, //
+        0.3, 0.4, 0.6,
+    ];
+    Spectrogram {
+        width: 4,
+        height: 3,
+        data,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn bw_quantisation_saturates() {
+    let sp = ramp();
+    let img = sp.as_image_bw_raw();
+
+    assert_eq!(img.get_pixel(0, 0)[0], 0);
+    assert_eq!(img.get_pixel(1, 1)[0], 255);
+    assert_eq!(img.get_pixel(2, 1)[0], 255);
+    assert_eq!(img.get_pixel(0, 2)[0], 0);
+
+    let img = sp.as_image_bw();
+    assert_eq!(img.get_pixel(1, 1)[0], 255);
+    assert_eq!(img.get_pixel(1, 0)[0], 191);
+}
+
+#[test]
+fn png16_roundtrip() {
+    let sp = ramp();
+    let img = sp.as_image_bw16();
+
+    let mut buf = Vec::new();
+    write_png16(&img, &mut buf).unwrap();
+
+    let mut decoder = png::Decoder::new(&buf[..]);
+    decoder.set_transformations(png::Transformations::IDENTITY);
+    let (info, mut reader) = decoder.read_info().unwrap();
+    assert_eq!(info.bit_depth, png::BitDepth::Sixteen);
+    assert_eq!((info.width, info.height), (4, 3));
+
+    let mut bytes = vec![0; info.buffer_size()];
+    reader.next_frame(&mut bytes).unwrap();
+    let decoded: Vec<u16> = bytes
+        .chunks(2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+        .collect();
+
+    assert_eq!(decoded, img.into_raw());
+}
+
+#[test]
+fn tiff_f32_is_lossless() {
+    let sp = ramp();
+    let img = sp.as_image_f32_raw();
+
+    let mut buf = Cursor::new(Vec::new());
+    write_tiff_f32(&img, &mut buf).unwrap();
+
+    let bytes = buf.into_inner();
+    let mut decoder = tiff::decoder::Decoder::new(Cursor::new(&bytes[..])).unwrap();
+    assert_eq!(decoder.dimensions().unwrap(), img.dimensions());
+    assert_eq!(decoder.get_tag_u32(Tag::BitsPerSample).unwrap(), 32);
+    assert_eq!(decoder.find_tag_u32(Tag::Unknown(339)).unwrap(), Some(3));
+
+    // The decoder only yields integer samples, so the strips are read using the offsets it reports.
+    let offsets = decoder.get_tag_u32_vec(Tag::StripOffsets).unwrap();
+    let counts = decoder.get_tag_u32_vec(Tag::StripByteCounts).unwrap();
+    let little_endian = &bytes[..2] == b"II";
+    let samples: Vec<f32> = offsets
+        .iter()
+        .zip(counts.iter())
+        .flat_map(|(&offset, &count)| bytes[offset as usize..(offset + count) as usize].chunks(4))
+        .map(|b| {
+            let b = [b[0], b[1], b[2], b[3]];
+            if little_endian {
+                f32::from_le_bytes(b)
+            } else {
+                f32::from_be_bytes(b)
+            }
+        })
+        .collect();
+
+    let expected: Vec<f32> = sp.data.iter().map(|v| *v as f32).collect();
+    assert_eq!(samples, expected);
+}
+
+#[test]
+fn png_metadata_roundtrip() {
+    let sp = ramp();
+
+    for orientation in &[Orientation::Image, Orientation::Raw] {
+        let mut metadata = ImageMetadata::new(&sp, *orientation);
+        // Non Latin-1 names have to go in an iTXt chunk.
+        metadata.source = Some("Caravan – 1937.mp3".to_string());
+        metadata.source_hash = Some(tizol::content_hash(b"caravan"));
+
+        let mut buf = Vec::new();
+        sp.write_png_with_metadata(&metadata, BitDepth::Sixteen, &mut buf)
+            .unwrap();
+        let (decoded, decoded_metadata) = Spectrogram::read_png_with_metadata(&buf[..]).unwrap();
+
+        assert_eq!(decoded_metadata, metadata);
+        assert_eq!((decoded.width, decoded.height), (sp.width, sp.height));
+        for (a, b) in decoded.data.iter().zip(sp.data.iter()) {
+            assert!((a - b.clamp(0.0, 1.0)).abs() < 1e-4);
+        }
+
+        let mut buf = Vec::new();
+        sp.write_png_with_metadata(&metadata, BitDepth::Eight, &mut buf)
+            .unwrap();
+        let (decoded, _) = Spectrogram::read_png_with_metadata(&buf[..]).unwrap();
+        assert_eq!(decoded.as_image_bw_raw().into_raw(), sp.as_image_bw_raw().into_raw());
+    }
+}
+
+#[test]
+fn png_metadata_db_range_roundtrip() {
+    let sp = ramp();
+
+    // The ramp covers the default 80 dB below the reference, only part of which is kept.
+    let mut metadata = ImageMetadata::new(&sp, Orientation::Raw);
+    metadata.db_range = (-40.0, -10.0);
+
+    let mut buf = Vec::new();
+    sp.write_png_with_metadata(&metadata, BitDepth::Sixteen, &mut buf)
+        .unwrap();
+    let (decoded, decoded_metadata) = Spectrogram::read_png_with_metadata(&buf[..]).unwrap();
+
+    assert_eq!(decoded_metadata.db_range, (-40.0, -10.0));
+    assert_eq!(decoded.parameters.unwrap().top_db, 40.0);
+    for (a, b) in decoded.data.iter().zip(sp.data.iter()) {
+        let db = ((b - 1.0) * 80.0).clamp(-40.0, -10.0);
+        assert!((a - (1.0 + db / 40.0)).abs() < 1e-4);
+    }
+}
+
+#[test]
+fn png_without_metadata_is_rejected() {
+    let mut buf = Vec::new();
+    write_png16(&ramp().as_image_bw16(), &mut buf).unwrap();
+
+    assert!(Spectrogram::read_png_with_metadata(&buf[..]).is_err());
+}
+
+#[test]
+fn from_image_roundtrip() {
+    let sp = ramp();
+
+    let bw = Spectrogram::from_image(&sp.as_image_bw(), Orientation::Image);
+    let raw = Spectrogram::from_image(&sp.as_image_bw_raw(), Orientation::Raw);
+    let bw16 = Spectrogram::from_image16(&sp.as_image_bw16(), Orientation::Image);
+
+    for decoded in &[bw, raw, bw16] {
+        assert_eq!((decoded.width, decoded.height), (sp.width, sp.height));
+        for (a, b) in decoded.data.iter().zip(sp.data.iter()) {
+            assert!((a - b.clamp(0.0, 1.0)).abs() <= 0.5 / 255.0);
+        }
+    }
+}
+
+#[test]
+fn legacy_png_is_loaded_without_metadata() {
+    // Legacy images truncated `value * 256`, rather than rounding `value * 255`
+    let legacy: Vec<u8> = vec![0, 64, 128, 192, 16, 32];
+    let img = image::GrayImage::from_raw(3, 2, legacy.clone()).unwrap();
+
+    let mut buf = Vec::new();
+    image::png::PNGEncoder::new(&mut buf)
+        .encode(&legacy[..], 3, 2, image::ColorType::Gray(8))
+        .unwrap();
+
+    let (decoded, metadata) = Spectrogram::read_image(&buf[..], Orientation::Raw).unwrap();
+    assert!(metadata.is_none());
+    assert_eq!((decoded.width, decoded.height), (2, 3));
+    assert_eq!(decoded.data, Spectrogram::from_legacy_image(&img, Orientation::Raw).data);
+    assert_eq!(decoded.data[2], 128.5 / 256.0);
+}
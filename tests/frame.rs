@@ -0,0 +1,72 @@
+mod common;
+
+use common::sine;
+use tizol::frame;
+use tizol::{Spectrogram, SAMPLE_RATE};
+
+#[test]
+fn features_align_with_spectrogram_columns() {
+    let samples = sine(440.0, 1.0);
+    for &(window_size, step_size) in &[(2048, 512), (1024, 300), (512, 512)] {
+        let builder = Spectrogram::builder()
+            .window_size(window_size)
+            .step_size(step_size);
+        let spectrogram = builder.build(&samples);
+
+        assert_eq!(builder.rms(&samples).len(), spectrogram.width as usize);
+        assert_eq!(
+            builder.zero_crossing_rate(&samples).len(),
+            spectrogram.width as usize
+        );
+    }
+}
+
+#[test]
+fn rms_of_a_sine() {
+    let samples: Vec<f64> = sine(440.0, 1.0).iter().map(|s| 0.5 * s).collect();
+    let rms = frame::rms(&samples, 2048, 512);
+    for value in rms {
+        assert!((value - 0.5 / 2f64.sqrt()).abs() < 1e-3, "{}", value);
+    }
+}
+
+#[test]
+fn rms_of_silence_and_constant() {
+    assert!(frame::rms(&vec![0.0; 4096], 1024, 256)
+        .iter()
+        .all(|v| *v == 0.0));
+    assert!(frame::rms(&vec![-0.25; 4096], 1024, 256)
+        .iter()
+        .all(|v| (v - 0.25).abs() < 1e-12));
+}
+
+#[test]
+fn zero_crossing_rate_of_a_sine() {
+    // A sine crosses zero twice per cycle
+    let frequency = 1000.0;
+    let expected = 2.0 * frequency / SAMPLE_RATE as f64;
+    for value in frame::zero_crossing_rate(&sine(frequency, 1.0), 2048, 512) {
+        assert!((value - expected).abs() < 2.0 / 2048.0, "{}", value);
+    }
+}
+
+#[test]
+fn zero_crossing_rate_of_an_alternating_signal() {
+    let samples: Vec<f64> = (0..16)
+        .map(|i| if i % 2 == 0 { 1.0 } else { -1.0 })
+        .collect();
+    assert_eq!(
+        frame::zero_crossing_rate(&samples, 8, 8),
+        vec![7.0 / 8.0; 2]
+    );
+
+    // Tiny values count as zero, which counts as positive
+    let samples = vec![1.0, 1e-12, -1e-12, 0.0, 1.0, -1.0, 0.0, 1.0];
+    assert_eq!(frame::zero_crossing_rate(&samples, 8, 8), vec![2.0 / 8.0]);
+}
+
+#[test]
+fn too_few_samples_for_a_frame() {
+    assert!(frame::rms(&[1.0; 100], 2048, 512).is_empty());
+    assert!(frame::zero_crossing_rate(&[1.0; 100], 2048, 512).is_empty());
+}
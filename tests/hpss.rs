@@ -0,0 +1,170 @@
+mod common;
+
+use common::sine;
+use tizol::builder::Scaling;
+use tizol::hpss::{Hpss, Mask};
+use tizol::normalisation::Normalisation;
+use tizol::stft::inplace::STFT;
+use tizol::stft::inverse::ISTFT;
+use tizol::stft::WindowType;
+use tizol::Spectrogram;
+
+/// A sine, with a click every quarter of a second
+fn sine_and_clicks() -> Vec<f64> {
+    sine(440.0, 1.0)
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            let click = if i % 11025 == 5000 { 1.0 } else { 0.0 };
+            0.5 * s + click
+        })
+        .collect()
+}
+
+fn small_builder() -> tizol::builder::SpectrogramBuilder {
+    Spectrogram::builder().window_size(1024).step_size(256)
+}
+
+#[test]
+fn inverse_stft_round_trip() {
+    let samples = sine_and_clicks();
+    let (window_size, step_size) = (1024, 256);
+    let stft = STFT::<f64>::new(WindowType::Hanning, window_size, step_size);
+    let istft = ISTFT::<f64>::new(WindowType::Hanning, window_size, step_size);
+
+    let columns = stft.par_iter_complex_stft(&samples);
+    let restored = istft.istft(&columns[..], samples.len());
+    assert_eq!(restored.len(), samples.len());
+
+    let covered = (columns.len() - 1) * step_size + window_size;
+    for i in window_size..covered - window_size {
+        assert!((restored[i] - samples[i]).abs() < 1e-9, "{}", i);
+    }
+    assert!(restored[covered..].iter().all(|s| *s == 0.0));
+}
+
+#[test]
+fn soft_masks_sum_to_one() {
+    let magnitudes: Vec<f64> = (0..64 * 10).map(|i| ((i * 37) % 11) as f64).collect();
+    let (harmonic, percussive) = Hpss::default().masks(&magnitudes[..], 64);
+    for (h, p) in harmonic.iter().zip(percussive.iter()) {
+        assert!((h + p - 1.0).abs() < 1e-12);
+    }
+
+    // Cells where both estimates are zero are split evenly
+    let (harmonic, percussive) = Hpss::default().masks(&vec![0.0; 64][..], 8);
+    assert!(harmonic.iter().chain(percussive.iter()).all(|m| *m == 0.5));
+}
+
+#[test]
+fn hard_masks_and_margins() {
+    let magnitudes: Vec<f64> = (0..64 * 10).map(|i| ((i * 37) % 11) as f64).collect();
+    let hpss = Hpss {
+        mask: Mask::Hard,
+        ..Default::default()
+    };
+    let (harmonic, percussive) = hpss.masks(&magnitudes[..], 64);
+    for (h, p) in harmonic.iter().zip(percussive.iter()) {
+        assert!(*h == 0.0 || *h == 1.0);
+        assert!(*p == 0.0 || *p == 1.0);
+        assert!(h + p <= 1.0);
+    }
+
+    // Margins leave a residual that is in neither component
+    let hpss = Hpss {
+        harmonic_margin: 2.0,
+        percussive_margin: 2.0,
+        ..Default::default()
+    };
+    let (harmonic, percussive) = hpss.masks(&magnitudes[..], 64);
+    assert!(harmonic
+        .iter()
+        .zip(percussive.iter())
+        .all(|(h, p)| h + p <= 1.0));
+    assert!(harmonic
+        .iter()
+        .zip(percussive.iter())
+        .any(|(h, p)| h + p < 0.99));
+}
+
+#[test]
+fn separates_lines() {
+    // A horizontal line in bin 5, and a vertical line in frame 20
+    let (width, height) = (41, 32);
+    let mut data = vec![0.0; width * height];
+    for t in 0..width {
+        data[t * height + 5] = 1.0;
+    }
+    for bin in 0..height {
+        data[20 * height + bin] = 1.0;
+    }
+    let spectrogram = Spectrogram {
+        width: width as u32,
+        height: height as u32,
+        data,
+        ..Default::default()
+    };
+
+    let hpss = Hpss {
+        harmonic_kernel: 9,
+        percussive_kernel: 9,
+        ..Default::default()
+    };
+    let (harmonic, percussive) = spectrogram.hpss(&hpss);
+    assert_eq!(harmonic.parameters.as_ref().unwrap().component, "harmonic");
+    assert_eq!(
+        percussive.parameters.as_ref().unwrap().component,
+        "percussive"
+    );
+
+    assert!(harmonic.get(3, 5).unwrap() > 0.99);
+    assert!(percussive.get(3, 5).unwrap() < 0.01);
+    assert!(percussive.get(20, 12).unwrap() > 0.99);
+    assert!(harmonic.get(20, 12).unwrap() < 0.01);
+}
+
+#[test]
+fn build_hpss_matches_build() {
+    let samples = sine_and_clicks();
+    let builder = small_builder()
+        .scaling(Scaling::Magnitude)
+        .normalisation(Normalisation::None);
+    let spectrogram = builder.build(&samples);
+    let (harmonic, percussive) = builder.build_hpss(&Hpss::default(), &samples);
+
+    for component in &[&harmonic, &percussive] {
+        assert_eq!(component.width, spectrogram.width);
+        assert_eq!(component.height, spectrogram.height);
+    }
+    assert_eq!(harmonic.parameters.unwrap().component, "harmonic");
+
+    // With soft masks and no margins, the components add up to the original
+    for ((h, p), v) in harmonic
+        .data
+        .iter()
+        .zip(percussive.data.iter())
+        .zip(spectrogram.data.iter())
+    {
+        assert!((h + p - v).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn separates_audio() {
+    let samples = sine_and_clicks();
+    let (harmonic, percussive) = small_builder().separate(&Hpss::default(), &samples);
+    assert_eq!(harmonic.len(), samples.len());
+    assert_eq!(percussive.len(), samples.len());
+
+    // The components add up to the original, away from the edges
+    for i in 1024..40000 {
+        assert!((harmonic[i] + percussive[i] - samples[i]).abs() < 1e-9);
+    }
+
+    // The clicks are percussive, and the sine between them harmonic
+    let click = 16025;
+    assert!(percussive[click] > 0.5, "{}", percussive[click]);
+    let energy = |s: &[f64]| s.iter().map(|v| v * v).sum::<f64>();
+    let between = 19000..21000;
+    assert!(energy(&harmonic[between.clone()]) > 10.0 * energy(&percussive[between]));
+}
@@ -0,0 +1,68 @@
+use tizol::{Spectrogram, SAMPLE_RATE, STEP_SIZE, WINDOW_SIZE};
+
+/// A spectrogram where each value encodes its own position, as `frame + bin / 10`
+fn numbered(width: u32, height: u32) -> Spectrogram {
+    let data = (0..width)
+        .flat_map(|c| (0..height).map(move |r| c as f64 + r as f64 / 10.0))
+        .collect();
+    Spectrogram {
+        width,
+        height,
+        data,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn columns_rows_and_values() {
+    let sp = numbered(4, 3);
+
+    assert_eq!(sp.column(2), &[2.0, 2.1, 2.2]);
+    assert_eq!(sp.columns().count(), 4);
+    assert_eq!(sp.row(1).collect::<Vec<_>>(), vec![0.1, 1.1, 2.1, 3.1]);
+    assert_eq!(sp.get(3, 2), Some(3.2));
+    assert_eq!(sp.get(4, 0), None);
+    assert_eq!(sp.get(0, 3), None);
+}
+
+#[test]
+fn time_and_frequency_conversions() {
+    let samples: Vec<f64> = (0..44100).map(|i| (i as f64 * 0.05).sin()).collect();
+    let sp = Spectrogram::from_buffer(&samples);
+
+    let hop = STEP_SIZE as f64 / SAMPLE_RATE as f64;
+    assert!((sp.frame_to_seconds(10) - 10.0 * hop).abs() < 1e-12);
+    assert_eq!(sp.seconds_to_frame(10.5 * hop), 10);
+    assert_eq!(sp.seconds_to_frame(-1.0), 0);
+
+    let bin_width = SAMPLE_RATE as f64 / WINDOW_SIZE as f64;
+    assert!((sp.bin_to_hz(100) - 100.0 * bin_width).abs() < 1e-9);
+    assert_eq!(sp.hz_to_bin(100.4 * bin_width), 100);
+    assert_eq!(sp.hz_to_bin(sp.bin_to_hz(37)), 37);
+
+    // Spectrograms without parameters use the defaults of `from_buffer`
+    let bare = numbered(4, 3);
+    assert!((bare.frame_to_seconds(10) - 10.0 * hop).abs() < 1e-12);
+}
+
+#[test]
+fn slicing() {
+    let samples: Vec<f64> = (0..44100).map(|i| (i as f64 * 0.05).sin()).collect();
+    let sp = Spectrogram::from_buffer(&samples);
+
+    let frames = sp.slice_frames(5..10);
+    assert_eq!((frames.width, frames.height), (5, sp.height));
+    assert_eq!(frames.column(0), sp.column(5));
+
+    let bins = sp.slice_bins(100..200);
+    assert_eq!((bins.width, bins.height), (sp.width, 100));
+    assert_eq!(bins.get(3, 0), sp.get(3, 100));
+    // Frequencies are preserved across the slice
+    assert!((bins.bin_to_hz(0) - sp.bin_to_hz(100)).abs() < 1e-9);
+    assert_eq!(bins.hz_to_bin(sp.bin_to_hz(150)), 50);
+
+    // Physical ranges are clamped to the spectrogram
+    let all = sp.slice_seconds(-1.0, 1000.0).slice_hz(-10.0, 1e6);
+    assert_eq!((all.width, all.height), (sp.width, sp.height));
+    assert_eq!(all.data, sp.data);
+}
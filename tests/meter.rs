@@ -0,0 +1,101 @@
+use tizol::meter::Meter;
+use tizol::Spectrogram;
+
+const BEAT: usize = 20;
+const FIRST_BEAT: usize = 10;
+/// The first downbeat is the second beat
+const FIRST_DOWNBEAT: usize = FIRST_BEAT + BEAT;
+const BAR: usize = 4 * BEAT;
+/// Two bars of intro before the first phrase
+const INTRO_BARS: usize = 2;
+const PHRASES: usize = 9;
+
+/// A beat grid, novelty curve and chromagram of a tune in 4/4, with a pickup beat, two bars of intro, and then 8-bar phrases (whose chords change more at the start of each phrase than within it), with a new chorus starting at the second phrase, and every four phrases after that
+fn tune() -> (Vec<usize>, Vec<f64>, Spectrogram) {
+    let bars = INTRO_BARS + PHRASES * 8;
+    let end = FIRST_DOWNBEAT + bars * BAR;
+    let beats: Vec<usize> = (0..=bars * 4).map(|b| FIRST_BEAT + b * BEAT).collect();
+
+    let mut novelty = vec![0.0; end];
+    for (i, beat) in beats.iter().enumerate() {
+        novelty[*beat] = if i % 4 == 1 { 1.0 } else { 0.5 };
+    }
+    for phrase in (1..PHRASES).step_by(4) {
+        novelty[FIRST_DOWNBEAT + (INTRO_BARS + phrase * 8) * BAR] = 3.0;
+    }
+
+    let mut data = vec![0.0; end * 12];
+    for frame in 0..end {
+        let column = &mut data[frame * 12..(frame + 1) * 12];
+        if frame < FIRST_DOWNBEAT + INTRO_BARS * BAR {
+            column[11] = 1.0;
+            continue;
+        }
+        let bar = (frame - FIRST_DOWNBEAT) / BAR - INTRO_BARS;
+        let root = (bar / 8) * 5 % 12;
+        column[root] = 1.0;
+        column[(root + if bar.is_multiple_of(2) { 7 } else { 4 }) % 12] = 0.3;
+    }
+
+    let chroma = Spectrogram {
+        width: end as u32,
+        height: 12,
+        data,
+        ..Default::default()
+    };
+    (beats, novelty, chroma)
+}
+
+#[test]
+fn finds_downbeats_phrases_and_choruses() {
+    let (beats, novelty, chroma) = tune();
+    let grid = Meter::default().analyse(&beats, &novelty, &chroma);
+    let end = chroma.width as usize;
+
+    // Downbeats are every fourth beat, from the second
+    assert_eq!(grid.downbeats[0], FIRST_DOWNBEAT);
+    assert!(grid.downbeats.windows(2).all(|d| d[1] - d[0] == BAR));
+
+    // The pickup beat is a partial bar
+    assert_eq!(grid.bars[0], FIRST_BEAT..FIRST_DOWNBEAT);
+    assert_eq!(grid.bars[1], FIRST_DOWNBEAT..FIRST_DOWNBEAT + BAR);
+    assert_eq!(grid.bars.last().unwrap().end, end);
+
+    // The intro is a partial phrase
+    let first_phrase = FIRST_DOWNBEAT + INTRO_BARS * BAR;
+    assert_eq!(grid.phrases[0], FIRST_BEAT..first_phrase);
+    assert_eq!(grid.phrases[1], first_phrase..first_phrase + 8 * BAR);
+    assert_eq!(grid.phrases.len(), PHRASES + 1);
+
+    // Choruses start at the second phrase
+    let first_chorus = first_phrase + 8 * BAR;
+    assert_eq!(grid.choruses[0], FIRST_BEAT..first_chorus);
+    assert_eq!(grid.choruses[1], first_chorus..first_chorus + 32 * BAR);
+    assert_eq!(grid.choruses[2], first_chorus + 32 * BAR..end);
+}
+
+#[test]
+fn other_meters() {
+    let (beats, novelty, chroma) = tune();
+    let meter = Meter {
+        beats_per_bar: 2,
+        ..Default::default()
+    };
+    let grid = meter.analyse(&beats, &novelty, &chroma);
+    assert!(grid.downbeats.windows(2).all(|d| d[1] - d[0] == 2 * BEAT));
+    assert_eq!(grid.downbeats[0], FIRST_DOWNBEAT);
+}
+
+#[test]
+fn no_beats() {
+    let chroma = Spectrogram {
+        width: 100,
+        height: 12,
+        data: vec![0.0; 1200],
+        ..Default::default()
+    };
+    let grid = Meter::default().analyse(&[], &[0.0; 100], &chroma);
+    assert!(grid.downbeats.is_empty());
+    assert_eq!(grid.bars, vec![]);
+    assert_eq!(grid.phrases, vec![]);
+}
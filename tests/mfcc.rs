@@ -0,0 +1,121 @@
+mod common;
+
+use common::{magnitudes, sine};
+use tizol::mel::{self, Mel};
+use tizol::mfcc::Mfcc;
+use tizol::{Spectrogram, SAMPLE_RATE};
+
+/// A single coefficient spectrogram, with the given values in each frame
+fn row(values: &[f64]) -> Spectrogram {
+    Spectrogram {
+        width: values.len() as u32,
+        height: 1,
+        data: values.to_vec(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn mel_scales() {
+    // The Slaney scale is linear up to 1kHz, which is 15 mels
+    assert!((mel::hz_to_mel(1000.0, false) - 15.0).abs() < 1e-9);
+    assert!((mel::hz_to_mel(200.0, false) - 3.0).abs() < 1e-9);
+    assert!((mel::hz_to_mel(1000.0, true) - 999.985_6).abs() < 1e-3);
+
+    for hz in &[0.0, 440.0, 1000.0, 8000.0] {
+        for htk in &[false, true] {
+            let roundtrip = mel::mel_to_hz(mel::hz_to_mel(*hz, *htk), *htk);
+            assert!((roundtrip - hz).abs() < 1e-6);
+        }
+    }
+}
+
+#[test]
+fn mel_spectrogram() {
+    let sp = magnitudes(&sine(440.0, 1.0));
+    let mel = sp.mel_spectrogram(&Mel::default());
+    assert_eq!((mel.width, mel.height), (sp.width, 128));
+
+    // The loudest band is the one centred nearest to the tone
+    let frequencies = Mel::default().frequencies(SAMPLE_RATE);
+    let column = mel.column(mel.width as usize / 2);
+    let loudest = (0..column.len())
+        .max_by(|a, b| column[*a].partial_cmp(&column[*b]).unwrap())
+        .unwrap();
+    assert!(frequencies[loudest] < 440.0 && frequencies[loudest + 2] > 440.0);
+
+    // Filters overlap, so every frequency within the filterbank is covered
+    let bins: Vec<f64> = (0..100).map(|i| 100.0 + i as f64 * 100.0).collect();
+    let filterbank = Mel::default().filterbank(&bins[..], SAMPLE_RATE);
+    for b in 0..bins.len() {
+        assert!(filterbank.iter().any(|weights| weights[b] > 0.0));
+    }
+}
+
+#[test]
+fn mel_bin_to_hz() {
+    let sp = magnitudes(&sine(440.0, 1.0));
+    for mel in &[
+        Mel::default(),
+        Mel {
+            n_mels: 40,
+            fmin: 100.0,
+            fmax: Some(8000.0),
+            htk: true,
+        },
+    ] {
+        let mel_sp = sp.mel_spectrogram(mel);
+        let frequencies = mel.frequencies(SAMPLE_RATE);
+
+        // Rows are the centres of the bands, not STFT bins
+        for band in 0..mel.n_mels {
+            assert!((mel_sp.bin_to_hz(band) - frequencies[band + 1]).abs() < 1e-6);
+            assert_eq!(mel_sp.hz_to_bin(frequencies[band + 1]), band);
+        }
+
+        // Slicing keeps the frequencies of the remaining bands
+        let sliced = mel_sp.slice_bins(10..20);
+        for band in 0..10 {
+            assert!((sliced.bin_to_hz(band) - mel_sp.bin_to_hz(band + 10)).abs() < 1e-6);
+        }
+    }
+
+    // Coefficients aren't mel bands
+    let mfcc = sp.mfcc(&Mfcc::default());
+    assert!(mfcc.parameters.unwrap().mel_scale.is_empty());
+}
+
+#[test]
+fn mfcc_shape_and_liftering() {
+    // Liftering scales each coefficient independently
+    let sp = magnitudes(&sine(440.0, 1.0));
+    let mfcc = sp.mfcc(&Mfcc::default());
+    assert_eq!((mfcc.width, mfcc.height), (sp.width, 20));
+    assert!(mfcc.data.iter().all(|v| v.is_finite()));
+
+    let liftered = sp.mfcc(&Mfcc {
+        lifter: 22.0,
+        ..Default::default()
+    });
+    for n in 0..20 {
+        let scale = 1.0 + 11.0 * (std::f64::consts::PI * (n + 1) as f64 / 22.0).sin();
+        assert!((liftered.get(5, n).unwrap() - scale * mfcc.get(5, n).unwrap()).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn deltas_of_polynomials() {
+    // The first derivative of a line is its slope, everywhere (including the edges)
+    let line: Vec<f64> = (0..20).map(|i| 3.0 * i as f64 + 1.0).collect();
+    let delta = row(&line[..]).delta(9, 1);
+    assert!(delta.data.iter().all(|v| (v - 3.0).abs() < 1e-9));
+
+    // The second derivative of a parabola is constant
+    let parabola: Vec<f64> = (0..20).map(|i| 0.5 * (i * i) as f64 - i as f64).collect();
+    let delta2 = row(&parabola[..]).delta(5, 2);
+    assert!(delta2.data.iter().all(|v| (v - 1.0).abs() < 1e-9));
+
+    // Deltas of a constant are zero
+    let delta = row(&[2.0; 10]).delta(3, 1);
+    assert!(delta.data.iter().all(|v| v.abs() < 1e-12));
+}
@@ -0,0 +1,79 @@
+use tizol::normalisation::Normalisation;
+use tizol::Spectrogram;
+
+fn assert_close(a: &[f64], b: &[f64]) {
+    assert_eq!(a.len(), b.len());
+    for (x, y) in a.iter().zip(b.iter()) {
+        assert!((x - y).abs() < 1e-9, "{:?} != {:?}", a, b);
+    }
+}
+
+/// Two frames of two bins, in dB
+fn buffer() -> Vec<f64> {
+    vec![-80.0, -40.0, -20.0, 0.0]
+}
+
+#[test]
+fn global_normalisations() {
+    let mut b = buffer();
+    Normalisation::None.apply(&mut b[..], 2);
+    assert_close(&b[..], &buffer()[..]);
+
+    let mut b = buffer();
+    Normalisation::MinMax.apply(&mut b[..], 2);
+    assert_close(&b[..], &[0.0, 0.5, 0.75, 1.0]);
+
+    // For non-positive dB values, the inverted normalisation of `from_buffer` is the same
+    let mut b = buffer();
+    Normalisation::InvertedMinMax.apply(&mut b[..], 2);
+    assert_close(&b[..], &[0.0, 0.5, 0.75, 1.0]);
+
+    let mut b = [-100.0, -60.0, -30.0, 10.0];
+    Normalisation::FixedRange {
+        min_db: -80.0,
+        max_db: -40.0,
+    }
+    .apply(&mut b[..], 2);
+    assert_close(&b[..], &[0.0, 0.5, 1.0, 1.0]);
+
+    let mut b: Vec<f64> = (0..=100).map(|v| v as f64).collect();
+    Normalisation::Percentile {
+        low: 10.0,
+        high: 60.0,
+    }
+    .apply(&mut b[..], 1);
+    assert_eq!(b[0], 0.0);
+    assert!((b[35] - 0.5).abs() < 1e-9);
+    assert_eq!(b[100], 1.0);
+}
+
+#[test]
+fn per_bin_and_frame_normalisations() {
+    let mut b = buffer();
+    Normalisation::PerBin.apply(&mut b[..], 2);
+    assert_close(&b[..], &[-1.0, -1.0, 1.0, 1.0]);
+
+    let mut b = buffer();
+    Normalisation::PerFrame.apply(&mut b[..], 2);
+    assert_close(&b[..], &[0.0, 1.0, 0.0, 1.0]);
+}
+
+#[test]
+fn builder_records_normalisation() {
+    let samples: Vec<f64> = (0..44100).map(|i| (i as f64 * 0.05).sin()).collect();
+    let sp = Spectrogram::builder()
+        .normalisation(Normalisation::FixedRange {
+            min_db: -80.0,
+            max_db: 0.0,
+        })
+        .build(&samples);
+
+    assert_eq!(sp.parameters.unwrap().normalisation, "fixed(-80,0)");
+    assert!(sp.data.iter().all(|v| *v >= 0.0 && *v <= 1.0));
+
+    let default = Spectrogram::from_buffer(&samples);
+    assert_eq!(
+        default.parameters.unwrap().normalisation,
+        Normalisation::default().to_string()
+    );
+}
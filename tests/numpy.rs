@@ -0,0 +1,77 @@
+use tizol::Spectrogram;
+
+use std::io::Cursor;
+
+fn spectrogram() -> Spectrogram {
+    // Three frames of two frequency bins
+    Spectrogram {
+        width: 3,
+        height: 2,
+        data: vec![0.0, 0.1, 1.0, 1.1, 2.0, 2.1],
+        ..Default::default()
+    }
+}
+
+/// Builds an `.npy` file the way `np.save` does, for a C ordered array of doubles.
+fn numpy_npy(shape: &str, values: &[f64]) -> Vec<u8> {
+    let mut header = format!(
+        "{{'descr': '<f8', 'fortran_order': False, 'shape': {}, }}",
+        shape
+    );
+    while (10 + header.len() + 1) % 64 != 0 {
+        header.push(' ');
+    }
+    header.push('\n');
+
+    let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+    bytes.extend(&(header.len() as u16).to_le_bytes());
+    bytes.extend(header.as_bytes());
+    bytes.extend(values.iter().flat_map(|v| v.to_le_bytes().to_vec()));
+    bytes
+}
+
+#[test]
+fn npy_layout_matches_librosa() {
+    let mut buf = Vec::new();
+    spectrogram().write_npy(&mut buf).unwrap();
+
+    let header_len = u16::from_le_bytes([buf[8], buf[9]]) as usize;
+    let header = std::str::from_utf8(&buf[10..10 + header_len]).unwrap();
+    assert_eq!(
+        header.trim_end(),
+        "{'descr': '<f8', 'fortran_order': True, 'shape': (2, 3), }"
+    );
+    assert_eq!((10 + header_len) % 64, 0);
+    assert_eq!(buf.len(), 10 + header_len + 6 * 8);
+
+    assert_eq!(Spectrogram::read_npy(&buf[..]).unwrap(), spectrogram());
+}
+
+#[test]
+fn read_c_ordered_npy() {
+    // The same spectrogram, stored row by row (frequency bin by frequency bin)
+    let bytes = numpy_npy("(2, 3)", &[0.0, 1.0, 2.0, 0.1, 1.1, 2.1]);
+
+    assert_eq!(Spectrogram::read_npy(&bytes[..]).unwrap(), spectrogram());
+}
+
+#[test]
+fn read_npy_rejects_other_shapes() {
+    let bytes = numpy_npy("(6,)", &[0.0; 6]);
+    assert!(Spectrogram::read_npy(&bytes[..]).is_err());
+}
+
+#[test]
+fn npz_roundtrip() {
+    let samples: Vec<f64> = (0..8192).map(|i| (i as f64 * 0.1).sin()).collect();
+    let sp = Spectrogram::from_buffer(&samples);
+
+    let mut buf = Cursor::new(Vec::new());
+    sp.write_npz(&mut buf).unwrap();
+    buf.set_position(0);
+    let decoded = Spectrogram::read_npz(buf).unwrap();
+
+    assert_eq!(decoded.data, sp.data);
+    assert_eq!((decoded.width, decoded.height), (sp.width, sp.height));
+    assert_eq!(decoded.parameters, sp.parameters);
+}
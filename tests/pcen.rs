@@ -0,0 +1,57 @@
+use tizol::normalisation::Normalisation;
+use tizol::pcen::Pcen;
+use tizol::{Spectrogram, SAMPLE_RATE, STEP_SIZE};
+
+#[test]
+fn constant_input_is_steady() {
+    let pcen = Pcen::default();
+    let mut data = vec![100.0; 2 * 10];
+    pcen.apply(&mut data[..], 2, SAMPLE_RATE, STEP_SIZE);
+
+    // With a constant input, the smoother is always at the input value
+    let gain = (100.0 + pcen.eps).powf(-pcen.gain);
+    let expected =
+        pcen.bias.powf(pcen.power) * (pcen.power * (100.0 * gain / pcen.bias).ln_1p()).exp_m1();
+    for v in data {
+        assert!((v - expected).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn onsets_are_emphasised() {
+    // A single bin, quiet for a while, with a sudden step up in level
+    let mut data: Vec<f64> = (0..200)
+        .map(|i| if i < 100 { 1.0 } else { 1000.0 })
+        .collect();
+    Pcen::default().apply(&mut data[..], 1, SAMPLE_RATE, STEP_SIZE);
+
+    // The step produces a peak, which decays as the gain control catches up
+    assert!(data[100] > data[99]);
+    assert!(data[100] > data[150]);
+    assert!(data[150] > data[199]);
+}
+
+#[test]
+fn smoothing_coefficient() {
+    let pcen = Pcen::default();
+    // `librosa.pcen` with sr=22050 and hop_length=512
+    let b = pcen.smoothing_coefficient(22050, 512);
+    assert!((b - 0.05638).abs() < 1e-4);
+}
+
+#[test]
+fn builder_uses_pcen() {
+    let samples: Vec<f64> = (0..44100).map(|i| (i as f64 * 0.05).sin()).collect();
+    let sp = Spectrogram::builder()
+        .pcen(Pcen::default())
+        .normalisation(Normalisation::MinMax)
+        .build(&samples);
+
+    let parameters = sp.parameters.unwrap();
+    assert_eq!(parameters.scaling, Pcen::default().to_string());
+    assert!(parameters.db_reference.is_empty());
+    assert!(sp.data.iter().all(|v| *v >= 0.0 && *v <= 1.0));
+
+    let default = Spectrogram::from_buffer(&samples);
+    assert_eq!(default.parameters.unwrap().scaling, "db");
+}
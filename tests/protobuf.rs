@@ -0,0 +1,82 @@
+mod common;
+
+use common::sine;
+use prost::Message;
+use tizol::{Encoding, Spectrogram, SCHEMA_VERSION};
+
+#[test]
+fn from_buffer_records_metadata() {
+    let sp = Spectrogram::from_buffer(&sine(440.0, 1.0));
+
+    assert_eq!(sp.schema_version, SCHEMA_VERSION);
+    assert!(sp.created > 0);
+
+    let parameters = sp.parameters.as_ref().unwrap();
+    assert_eq!(parameters.sample_rate, 44100);
+    assert_eq!(parameters.window, "Hanning");
+    assert_eq!(parameters.window_size, 2048);
+    assert_eq!(parameters.step_size, 512);
+    assert_eq!(parameters.top_db, 80.0);
+    assert_eq!(parameters.fmax, 22050.0);
+
+    let source = sp.source.as_ref().unwrap();
+    assert_eq!(source.duration, 1.0);
+    assert!(source.path.is_empty());
+}
+
+#[test]
+fn metadata_survives_encoding() {
+    let sp = Spectrogram::from_buffer(&sine(440.0, 0.5));
+
+    let mut buf = Vec::new();
+    sp.encode(&mut buf).unwrap();
+    let decoded = Spectrogram::decode(&buf[..]).unwrap();
+
+    assert_eq!(decoded, sp);
+}
+
+#[test]
+fn compact_encodings_roundtrip() {
+    let sp = Spectrogram::from_buffer(&sine(440.0, 0.5));
+
+    let mut double = Vec::new();
+    sp.encode(&mut double).unwrap();
+
+    // (encoding, maximum error)
+    let encodings = [
+        (Encoding::Double, 0.0),
+        (Encoding::Float, 1e-7),
+        (Encoding::Quantised8, 0.5 / 255.0),
+        (Encoding::Quantised16, 0.5 / 65535.0),
+        (Encoding::DeltaRle16, 0.5 / 65535.0),
+    ];
+
+    for (encoding, max_error) in encodings.iter() {
+        let mut buf = Vec::new();
+        sp.with_encoding(*encoding).unwrap().encode(&mut buf).unwrap();
+        if *encoding != Encoding::Double {
+            assert!(buf.len() < double.len(), "{:?} is not compact", encoding);
+        }
+        if *encoding == Encoding::Quantised8 {
+            assert!(buf.len() < double.len() / 6);
+        }
+
+        let decoded = Spectrogram::decode_expanded(&buf[..]).unwrap();
+        assert_eq!(decoded.encoding(), Encoding::Double);
+        assert_eq!(decoded.parameters, sp.parameters);
+        assert_eq!(decoded.data.len(), sp.data.len());
+        for (a, b) in decoded.data.iter().zip(sp.data.iter()) {
+            assert!((a - b).abs() <= *max_error, "{:?}: {} != {}", encoding, a, b);
+        }
+    }
+}
+
+#[test]
+fn truncated_compact_data_is_rejected() {
+    let sp = Spectrogram::from_buffer(&sine(440.0, 0.1));
+    let mut compact = sp.with_encoding(Encoding::DeltaRle16).unwrap();
+    let len = compact.data_quantised.len();
+    compact.data_quantised.truncate(len / 2);
+
+    assert!(compact.expand().is_err());
+}
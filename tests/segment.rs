@@ -0,0 +1,114 @@
+use tizol::segment::{checkerboard_novelty, Section, Segmenter, SELF_SIMILARITY};
+use tizol::Spectrogram;
+
+const BEAT: usize = 10;
+
+/// Frame-aligned features and energy of a tune whose sections (of `beats` beats each) have distinct pitch classes, and the given energy
+fn tune(sections: &[(usize, usize, f64)]) -> (Spectrogram, Vec<usize>, Vec<f64>) {
+    let mut data = Vec::new();
+    let mut energy = Vec::new();
+    for (beats, pitch_class, level) in sections {
+        for _ in 0..beats * BEAT {
+            let mut column = vec![0.0; 12];
+            column[*pitch_class] = 1.0;
+            column[(*pitch_class + 7) % 12] = 0.5;
+            data.extend(column);
+            energy.push(*level);
+        }
+    }
+    let width = energy.len();
+    let beats = (0..width).step_by(BEAT).collect();
+    let features = Spectrogram {
+        width: width as u32,
+        height: 12,
+        data,
+        ..Default::default()
+    };
+    (features, beats, energy)
+}
+
+#[test]
+fn labels_a_big_band_arrangement() {
+    let (features, beats, energy) = tune(&[
+        (16, 0, 0.3),
+        (32, 2, 0.5),
+        (32, 4, 0.4),
+        (32, 5, 0.9),
+        (32, 2, 0.5),
+        (16, 9, 0.2),
+    ]);
+    let structure = Segmenter::default().analyse(&features, &beats, &energy);
+
+    let labels: Vec<Section> = structure.segments.iter().map(|s| s.label).collect();
+    assert_eq!(
+        labels,
+        vec![
+            Section::Intro,
+            Section::Head,
+            Section::Solo,
+            Section::ShoutChorus,
+            Section::Head,
+            Section::Outro
+        ]
+    );
+
+    let starts: Vec<usize> = structure.segments.iter().map(|s| s.frames.start).collect();
+    assert_eq!(starts, vec![0, 160, 480, 800, 1120, 1440]);
+    assert_eq!(structure.segments[5].frames.end, 1600);
+    assert_eq!(structure.segments[1].columns, 16..48);
+    assert_eq!(Section::ShoutChorus.to_string(), "shout chorus");
+}
+
+#[test]
+fn similarity_matrix() {
+    let (features, beats, energy) = tune(&[(16, 0, 1.0), (16, 3, 1.0)]);
+    let structure = Segmenter::default().analyse(&features, &beats, &energy);
+    let ssm = &structure.similarity;
+
+    assert_eq!((ssm.width, ssm.height), (32, 32));
+    assert_eq!(ssm.parameters.as_ref().unwrap().scaling, SELF_SIMILARITY);
+    assert!(ssm.data.iter().all(|v| *v >= 0.0 && *v <= 1.0));
+    for i in 0..32 {
+        assert!((ssm.get(i, i).unwrap() - 1.0).abs() < 1e-12);
+        for j in 0..32 {
+            assert_eq!(ssm.get(i, j), ssm.get(j, i));
+        }
+    }
+    assert!(ssm.get(2, 20).unwrap() < 0.01);
+
+    // The matrix renders like any other spectrogram
+    assert_eq!(ssm.as_image_col().dimensions(), (32, 32));
+
+    // The novelty peaks at the boundary
+    let peak = (0..32)
+        .max_by(|a, b| {
+            structure.novelty[*a]
+                .partial_cmp(&structure.novelty[*b])
+                .unwrap()
+        })
+        .unwrap();
+    assert_eq!(peak, 16);
+    assert_eq!(structure.novelty[16], 1.0);
+    assert_eq!(structure.segments.len(), 2);
+    assert_eq!(structure.segments[0].label, Section::Head);
+    assert_eq!(structure.segments[1].label, Section::Solo);
+}
+
+#[test]
+fn uniform_tune_has_one_segment() {
+    let (features, beats, energy) = tune(&[(64, 0, 1.0)]);
+    let structure = Segmenter::default().analyse(&features, &beats, &energy);
+    assert_eq!(structure.segments.len(), 1);
+    assert_eq!(structure.segments[0].frames, 0..640);
+    assert_eq!(structure.segments[0].label, Section::Head);
+
+    let novelty = checkerboard_novelty(&structure.similarity, 8);
+    assert_eq!(novelty.len(), 64);
+}
+
+#[test]
+fn empty_features() {
+    let structure = Segmenter::default().analyse(&Spectrogram::default(), &[], &[]);
+    assert!(structure.segments.is_empty());
+    assert!(structure.novelty.is_empty());
+}
@@ -0,0 +1,62 @@
+#![cfg(feature = "serde")]
+
+use tizol::builder::SpectrogramBuilder;
+use tizol::db::Reference;
+use tizol::export::{ImageMetadata, Orientation};
+use tizol::stft::WindowType;
+use tizol::Spectrogram;
+
+#[test]
+fn spectrogram_json_roundtrip() {
+    let samples: Vec<f64> = (0..8192).map(|i| (i as f64 * 0.1).sin()).collect();
+    let sp = Spectrogram::from_buffer(&samples);
+
+    let json = serde_json::to_string(&sp).unwrap();
+    let decoded: Spectrogram = serde_json::from_str(&json).unwrap();
+
+    assert_eq!((decoded.width, decoded.height), (sp.width, sp.height));
+    assert_eq!(decoded.schema_version, sp.schema_version);
+    assert_eq!(decoded.created, sp.created);
+
+    // serde_json doesn't guarantee that floats survive a roundtrip exactly
+    let (parameters, expected) = (decoded.parameters.unwrap(), sp.parameters.unwrap());
+    assert_eq!(parameters.window, expected.window);
+    assert_eq!(parameters.window_size, expected.window_size);
+    assert!((parameters.amin - expected.amin).abs() < 1e-12);
+    for (a, b) in decoded.data.iter().zip(sp.data.iter()) {
+        assert!((a - b).abs() < 1e-12);
+    }
+}
+
+#[test]
+fn configuration_json_roundtrip() {
+    let json = serde_json::to_string(&WindowType::Blackman).unwrap();
+    assert_eq!(serde_json::from_str::<WindowType>(&json).unwrap(), WindowType::Blackman);
+
+    let sp = Spectrogram {
+        width: 1,
+        height: 1,
+        data: vec![0.5],
+        ..Default::default()
+    };
+    let metadata = ImageMetadata::new(&sp, Orientation::Raw);
+    let json = serde_json::to_string(&metadata).unwrap();
+    assert_eq!(serde_json::from_str::<ImageMetadata>(&json).unwrap(), metadata);
+}
+
+#[test]
+fn builder_json_roundtrip() {
+    let samples: Vec<f64> = (0..8192).map(|i| (i as f64 * 0.1).sin()).collect();
+    let builder = SpectrogramBuilder::new()
+        .window_type(WindowType::Blackman)
+        .reference(Reference::Value(1.0))
+        .top_db(None);
+
+    let json = serde_json::to_string(&builder).unwrap();
+    let decoded: SpectrogramBuilder = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded.build(&samples).data, builder.build(&samples).data);
+
+    // Function references only exist at runtime
+    let builder = builder.reference(Reference::Function(|values| values[0]));
+    assert!(serde_json::to_string(&builder).is_err());
+}
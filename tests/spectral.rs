@@ -0,0 +1,105 @@
+mod common;
+
+use common::{magnitudes, sine};
+use tizol::builder::Scaling;
+use tizol::cqt::Cqt;
+use tizol::normalisation::Normalisation;
+use tizol::spectral::{Aggregate, Contrast};
+use tizol::Spectrogram;
+
+/// Deterministic white noise, from a linear congruential generator
+fn noise() -> Vec<f64> {
+    let mut state: u32 = 12345;
+    (0..44100)
+        .map(|_| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (state >> 8) as f64 / (1u32 << 24) as f64 * 2.0 - 1.0
+        })
+        .collect()
+}
+
+#[test]
+fn descriptors_of_a_flat_spectrum() {
+    // Two frames of a flat spectrum, over bins at 0, 100, 200 and 300 Hz
+    let sp = Spectrogram {
+        width: 2,
+        height: 4,
+        data: vec![1.0; 8],
+        parameters: Some(tizol::Parameters {
+            sample_rate: 800,
+            fft_size: 8,
+            step_size: 2,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    assert_eq!(sp.spectral_centroid(), vec![150.0, 150.0]);
+    let bandwidth = (((150.0f64 * 150.0) + (50.0 * 50.0)) / 2.0).sqrt();
+    assert!(sp
+        .spectral_bandwidth()
+        .iter()
+        .all(|b| (b - bandwidth).abs() < 1e-9));
+    assert_eq!(sp.spectral_rolloff(0.5), vec![100.0, 100.0]);
+    assert_eq!(sp.spectral_rolloff(0.85), vec![300.0, 300.0]);
+    assert!(sp
+        .spectral_flatness()
+        .iter()
+        .all(|f| (f - 1.0).abs() < 1e-9));
+}
+
+#[test]
+fn tones_and_noise() {
+    let tone = magnitudes(&sine(1000.0, 1.0))
+        .spectral_descriptors()
+        .summary();
+    let noise = magnitudes(&noise()).spectral_descriptors().summary();
+
+    assert!((tone.centroid.mean - 1000.0).abs() < 50.0);
+    assert!(noise.centroid.mean > 5000.0);
+    assert!(tone.bandwidth.mean < noise.bandwidth.mean);
+    assert!(tone.rolloff.mean < noise.rolloff.mean);
+    assert!(tone.flatness.mean < 0.01);
+    assert!(noise.flatness.mean > 0.3);
+
+    // A tone stands out much more than noise in its band
+    assert_eq!(tone.contrast.len(), Contrast::default().n_bands + 1);
+    assert!(tone.contrast[3].mean > noise.contrast[3].mean);
+}
+
+#[test]
+fn aggregates() {
+    let aggregate = Aggregate::of(&[1.0, 2.0, 3.0, 4.0]);
+    assert_eq!(aggregate.mean, 2.5);
+    assert!((aggregate.std - 1.25f64.sqrt()).abs() < 1e-12);
+    assert_eq!((aggregate.min, aggregate.max), (1.0, 4.0));
+    assert_eq!(Aggregate::of(&[]), Aggregate::default());
+}
+
+#[test]
+fn contrast_of_bands_without_bins() {
+    let samples = sine(1000.0, 1.0);
+
+    // A spectrogram sliced below 6.4kHz has no bins in the top band
+    let sliced = magnitudes(&samples).slice_hz(0.0, 5000.0);
+    let summary = sliced.spectral_descriptors().summary();
+    assert_eq!(summary.contrast.len(), Contrast::default().n_bands + 1);
+    assert!(sliced
+        .spectral_contrast(&Contrast::default())
+        .row(Contrast::default().n_bands)
+        .all(|v| v.is_nan()));
+    assert_eq!(
+        summary.contrast[Contrast::default().n_bands],
+        Aggregate::default()
+    );
+    assert!(summary.contrast[3].mean > 0.0);
+
+    // Constant-Q spectrograms top out well below the highest band
+    let cqt = Spectrogram::builder()
+        .cqt(Cqt::default())
+        .scaling(Scaling::Magnitude)
+        .normalisation(Normalisation::None)
+        .build(&samples);
+    let summary = cqt.spectral_descriptors().summary();
+    assert_eq!(summary.contrast.len(), Contrast::default().n_bands + 1);
+}
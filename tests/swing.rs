@@ -0,0 +1,85 @@
+use tizol::swing::{Feel, Swing, SwingEstimate};
+
+/// An onset envelope with a beat every `period` frames, and a (weaker) second eighth note `offbeat` frames after each beat
+fn envelope(beats: usize, period: usize, offbeat: usize) -> (Vec<f64>, Vec<usize>) {
+    let mut envelope = vec![0.0; beats * period + 1];
+    let grid: Vec<usize> = (0..=beats).map(|b| b * period).collect();
+    for beat in &grid {
+        envelope[*beat] = 1.0;
+        if beat + offbeat < envelope.len() {
+            envelope[beat + offbeat] = 0.6;
+        }
+    }
+    (envelope, grid)
+}
+
+#[test]
+fn straight_eighths() {
+    let (envelope, beats) = envelope(32, 40, 20);
+    let swing = Swing::default().estimate(&envelope, &beats).unwrap();
+    assert!((swing.ratio - 1.0).abs() < 0.05, "{}", swing.ratio);
+    assert_eq!(swing.feel(), Feel::Straight);
+    assert!(swing.confidence > 0.5);
+}
+
+#[test]
+fn triplet_swing() {
+    // 27/13 is as close to 2:1 as a 40 frame beat allows
+    let (envelope, beats) = envelope(32, 40, 27);
+    let swing = Swing::default().estimate(&envelope, &beats).unwrap();
+    assert!((swing.ratio - 27.0 / 13.0).abs() < 0.1, "{}", swing.ratio);
+    assert_eq!(swing.feel(), Feel::Swing);
+}
+
+#[test]
+fn heavy_shuffle() {
+    let (envelope, beats) = envelope(32, 40, 30);
+    let swing = Swing::default().estimate(&envelope, &beats).unwrap();
+    assert!((swing.ratio - 3.0).abs() < 0.15, "{}", swing.ratio);
+    assert_eq!(swing.feel(), Feel::HeavyShuffle);
+    assert_eq!(swing.feel().to_string(), "heavy shuffle");
+}
+
+#[test]
+fn swing_curve() {
+    // Sixteen straight beats, then sixteen swung ones
+    let (straight, beats) = envelope(32, 40, 20);
+    let (swung, _) = envelope(32, 40, 27);
+    let mut combined = straight[..16 * 40].to_vec();
+    combined.extend_from_slice(&swung[16 * 40..]);
+
+    let analysis = Swing::default().analyse(&combined, &beats);
+    assert_eq!(analysis.sections.len(), 2);
+    assert_eq!(analysis.sections[0].start, 0);
+    assert_eq!(analysis.sections[0].end, 16 * 40);
+    assert_eq!(analysis.sections[1].start, 16 * 40);
+    assert_eq!(analysis.sections[1].end, 32 * 40);
+
+    let first = analysis.sections[0].swing.unwrap();
+    let second = analysis.sections[1].swing.unwrap();
+    assert_eq!(first.feel(), Feel::Straight);
+    assert_eq!(second.feel(), Feel::Swing);
+
+    // Uneven sections: the last one is shorter
+    let sections = Swing {
+        section_beats: 12,
+        ..Default::default()
+    }
+    .sections(&combined, &beats);
+    assert_eq!(sections.len(), 3);
+    assert_eq!(sections[2].start, 24 * 40);
+    assert_eq!(sections[2].end, 32 * 40);
+}
+
+#[test]
+fn no_beats_or_onsets() {
+    let swing = Swing::default();
+    assert_eq!(swing.estimate(&[0.0; 100], &[0, 40, 80]), None);
+    assert_eq!(swing.estimate(&[1.0; 100], &[10]), None);
+    assert!(swing.sections(&[1.0; 100], &[]).is_empty());
+
+    // Beats beyond the end of the envelope are ignored
+    let (envelope, beats) = envelope(4, 40, 20);
+    let estimate: Option<SwingEstimate> = swing.estimate(&envelope[..100], &beats);
+    assert!((estimate.unwrap().ratio - 1.0).abs() < 0.05);
+}
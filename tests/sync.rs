@@ -0,0 +1,83 @@
+use tizol::sync::Aggregation;
+use tizol::Spectrogram;
+
+/// A spectrogram with two bins: the frame index, and its square
+fn ramp(width: usize) -> Spectrogram {
+    Spectrogram {
+        width: width as u32,
+        height: 2,
+        data: (0..width)
+            .flat_map(|t| vec![t as f64, (t * t) as f64])
+            .collect(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn aggregates_between_beats() {
+    let spectrogram = ramp(10);
+    let beats = [2, 5, 9];
+    assert_eq!(spectrogram.beat_sync_frames(&beats), vec![0, 2, 5, 9]);
+
+    let mean = spectrogram.beat_sync(&beats, Aggregation::Mean);
+    assert_eq!((mean.width, mean.height), (4, 2));
+    assert_eq!(mean.column(0), &[0.5, 0.5]);
+    assert_eq!(mean.column(1), &[3.0, 29.0 / 3.0]);
+    assert_eq!(mean.column(2), &[6.5, (25.0 + 36.0 + 49.0 + 64.0) / 4.0]);
+    assert_eq!(mean.column(3), &[9.0, 81.0]);
+    assert_eq!(mean.parameters.as_ref().unwrap().aggregation, "mean");
+
+    let median = spectrogram.beat_sync(&beats, Aggregation::Median);
+    assert_eq!(median.column(1), &[3.0, 9.0]);
+    assert_eq!(median.column(2), &[6.5, 42.5]);
+
+    let max = spectrogram.beat_sync(&beats, Aggregation::Max);
+    assert_eq!(max.column(1), &[4.0, 16.0]);
+    assert_eq!(max.column(2), &[8.0, 64.0]);
+    assert_eq!(max.parameters.unwrap().aggregation, "max");
+}
+
+#[test]
+fn tempo_invariance() {
+    // The same pattern of chords, at two tempi
+    let chords = [0, 4, 7, 4];
+    let tune = |beat: usize| {
+        let mut data = Vec::new();
+        for chord in chords.iter() {
+            for _ in 0..beat {
+                let mut column = vec![0.0; 12];
+                column[*chord] = 1.0;
+                data.extend(column);
+            }
+        }
+        let spectrogram = Spectrogram {
+            width: (chords.len() * beat) as u32,
+            height: 12,
+            data,
+            ..Default::default()
+        };
+        let beats: Vec<usize> = (0..chords.len()).map(|b| b * beat).collect();
+        spectrogram.beat_sync(&beats, Aggregation::Mean)
+    };
+
+    let (slow, fast) = (tune(30), tune(17));
+    assert_eq!(slow.width, 4);
+    assert_eq!(slow.data, fast.data);
+}
+
+#[test]
+fn ignores_duplicate_unsorted_and_out_of_range_beats() {
+    let spectrogram = ramp(10);
+    assert_eq!(
+        spectrogram.beat_sync_frames(&[5, 0, 2, 2, 12]),
+        vec![0, 2, 5]
+    );
+    assert_eq!(
+        spectrogram.beat_sync(&[], Aggregation::Max).column(0),
+        &[9.0, 81.0]
+    );
+
+    let empty = Spectrogram::default().beat_sync(&[1, 2], Aggregation::Median);
+    assert_eq!(empty.width, 0);
+    assert!(empty.data.is_empty());
+}
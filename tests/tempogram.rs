@@ -0,0 +1,147 @@
+mod common;
+
+use common::magnitudes;
+use tizol::normalisation::Normalisation;
+use tizol::onset::Onset;
+use tizol::tempogram::{Tempogram, AUTOCORRELATION, FOURIER};
+use tizol::{Spectrogram, SAMPLE_RATE};
+
+/// Short bursts of noise-like clicks at a steady tempo
+fn clicks(bpm: f64, seconds: usize) -> Vec<f64> {
+    let period = (60.0 / bpm * SAMPLE_RATE as f64) as usize;
+    (0..SAMPLE_RATE as usize * seconds)
+        .map(|i| {
+            let offset = i % period;
+            if offset < 200 {
+                (1.0 - offset as f64 / 200.0) * ((i * 7919) % 13) as f64 / 6.0 - 1.0
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+/// An impulse train, with one onset every `period` frames
+fn impulses(period: usize, frames: usize) -> Vec<f64> {
+    (0..frames)
+        .map(|t| if t % period == 0 { 1.0 } else { 0.0 })
+        .collect()
+}
+
+/// A spectrogram with the default parameters, for envelopes that weren't computed from one
+fn source() -> Spectrogram {
+    Spectrogram::default()
+}
+
+#[test]
+fn onset_strength_peaks_at_clicks() {
+    let samples = clicks(120.0, 4);
+    let spectrogram = magnitudes(&samples);
+    let envelope = spectrogram.onset_strength(&Onset::default());
+
+    assert_eq!(envelope.len(), spectrogram.width as usize);
+    assert_eq!(envelope[0], 0.0);
+    assert!(envelope.iter().all(|v| *v >= 0.0));
+
+    // The strongest onsets are within a frame or so of each click
+    let max = envelope.iter().cloned().fold(0.0, f64::max);
+    for (t, v) in envelope.iter().enumerate() {
+        if *v > 0.5 * max {
+            let seconds = spectrogram.frame_to_seconds(t) + 2048.0 / SAMPLE_RATE as f64;
+            let phase = (seconds / 0.5).fract();
+            assert!(!(0.1..=0.9).contains(&phase), "{} {}", t, phase);
+        }
+    }
+}
+
+#[test]
+fn autocorrelation_tempogram_shape_and_axis() {
+    let envelope = impulses(43, 600);
+    let tempogram = Tempogram::default().autocorrelation(&envelope, &source());
+
+    assert_eq!(tempogram.width, 600);
+    assert_eq!(tempogram.height, 384);
+    let p = tempogram.parameters.as_ref().unwrap();
+    assert_eq!(p.scaling, AUTOCORRELATION);
+
+    // Row k is a lag of k frames
+    let frame_rate = SAMPLE_RATE as f64 / 512.0;
+    assert!(tempogram.bin_to_bpm(0).is_infinite());
+    assert!((tempogram.bin_to_bpm(43) - 60.0 * frame_rate / 43.0).abs() < 1e-9);
+
+    // Columns are normalised, with lag 0 the largest, and peaks at multiples of the period
+    let column = tempogram.column(300);
+    assert!((column[0] - 1.0).abs() < 1e-9);
+    assert!(column.iter().all(|v| *v >= -1e-9 && *v <= 1.0 + 1e-9));
+    assert!(column[43] > column[42] && column[43] > column[44]);
+    assert!(column[86] > column[85] && column[86] > column[87]);
+    assert!(column[20] < 1e-9);
+}
+
+#[test]
+fn fourier_tempogram_peaks_at_the_tempo() {
+    // A pulsing (but sinusoidal, so without harmonics) envelope
+    let envelope: Vec<f64> = (0..1000)
+        .map(|t| 1.0 + (2.0 * std::f64::consts::PI * t as f64 / 43.0).cos())
+        .collect();
+    let tempogram = Tempogram::default().fourier(&envelope, &source());
+
+    assert_eq!(tempogram.width, 1000);
+    assert_eq!(tempogram.height, 193);
+    assert_eq!(tempogram.parameters.as_ref().unwrap().scaling, FOURIER);
+
+    // Rows are linearly spaced, up to the Nyquist frequency of the envelope
+    let frame_rate = SAMPLE_RATE as f64 / 512.0;
+    let step = 60.0 * frame_rate / 384.0;
+    assert!((tempogram.bin_to_bpm(10) - 10.0 * step).abs() < 1e-9);
+    assert!((tempogram.bin_to_bpm(192) - 30.0 * frame_rate).abs() < 1e-9);
+
+    // Ignoring the DC rows (into which the constant offset leaks), the strongest row is the tempo of the pulses
+    let column = tempogram.column(500);
+    let peak = (3..column.len())
+        .max_by(|a, b| column[*a].partial_cmp(&column[*b]).unwrap())
+        .unwrap();
+    let bpm = 60.0 * frame_rate / 43.0;
+    assert!((tempogram.bin_to_bpm(peak) - bpm).abs() <= step, "{}", peak);
+}
+
+#[test]
+fn tempograms_render() {
+    let envelope = impulses(30, 200);
+    let tempogram = Tempogram { window_length: 64 }.fourier(&envelope, &source());
+    let normalised = tempogram.normalised(&Normalisation::MinMax);
+    assert_eq!(
+        normalised.parameters.as_ref().unwrap().normalisation,
+        "minmax"
+    );
+    assert!(normalised.data.iter().all(|v| *v >= 0.0 && *v <= 1.0));
+
+    let image = normalised.as_image_col();
+    assert_eq!(image.dimensions(), (200, 33));
+
+    let autocorrelation = Tempogram { window_length: 64 }.autocorrelation(&envelope, &source());
+    assert_eq!(autocorrelation.as_image_bw().dimensions(), (200, 64));
+}
+
+#[test]
+fn empty_envelope() {
+    let tempogram = Tempogram::default().autocorrelation(&[], &source());
+    assert_eq!(tempogram.width, 0);
+    assert!(tempogram.data.is_empty());
+    assert_eq!(Tempogram::default().fourier(&[], &source()).width, 0);
+}
+
+#[test]
+fn tempograms_keep_their_provenance() {
+    let mut spectrogram = magnitudes(&clicks(120.0, 2));
+    spectrogram.source.as_mut().unwrap().path = "clicks.wav".to_string();
+    let envelope = spectrogram.onset_strength(&Onset::default());
+
+    let tempogram = Tempogram { window_length: 64 }.autocorrelation(&envelope, &spectrogram);
+    assert_eq!(tempogram.source, spectrogram.source);
+    assert_eq!(tempogram.created, spectrogram.created);
+    assert_eq!(tempogram.schema_version, spectrogram.schema_version);
+
+    let fourier = Tempogram { window_length: 64 }.fourier(&envelope, &spectrogram);
+    assert_eq!(fourier.source.unwrap().path, "clicks.wav");
+}